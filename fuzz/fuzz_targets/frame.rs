@@ -0,0 +1,23 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate amqp;
+
+use amqp::frame::Frame;
+use amqp::protocol::{FRAME_HEADER, FRAME_METHOD};
+
+fuzz_target!(|data: &[u8]| {
+    let mut data = data;
+    while let Ok(Some((frame, size))) = Frame::decode(data) {
+        match frame.ty {
+            FRAME_METHOD => {
+                let _ = frame.method();
+            },
+            FRAME_HEADER => {
+                let _ = frame.to_content_header();
+            },
+            _ => (),
+        }
+        data = &data[size..];
+    }
+});
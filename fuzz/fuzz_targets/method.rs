@@ -0,0 +1,12 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate amqp;
+
+use amqp::content::ContentHeader;
+use amqp::protocol::AmqpMethod;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = AmqpMethod::decode(data);
+    let _ = ContentHeader::de(data);
+});
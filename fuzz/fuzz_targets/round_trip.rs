@@ -0,0 +1,78 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate amqp;
+extern crate arbitrary;
+
+use arbitrary::Unstructured;
+
+use amqp::method::{self, Method};
+use amqp::protocol::connection;
+use amqp::types::*;
+
+// Stays well below the decoder's nesting limit.
+const MAX_DEPTH: u32 = 8;
+
+fn table(u: &mut Unstructured, depth: u32) -> arbitrary::Result<Table> {
+    let mut table = Table::new();
+    for _ in 0..try!(u.int_in_range(0..=8)) {
+        let name: String = try!(u.arbitrary());
+        table.insert(name, try!(field_value(u, depth)));
+    }
+    Ok(table)
+}
+
+fn field_value(u: &mut Unstructured, depth: u32) -> arbitrary::Result<FieldValue> {
+    let max = if depth < MAX_DEPTH { 17 } else { 15 };
+    let value = match try!(u.int_in_range(0..=max)) {
+        0 => FieldValue::Bool(try!(u.arbitrary())),
+        1 => FieldValue::ShortShortInt(try!(u.arbitrary())),
+        2 => FieldValue::ShortShortUint(try!(u.arbitrary())),
+        3 => FieldValue::ShortInt(try!(u.arbitrary())),
+        4 => FieldValue::ShortUint(try!(u.arbitrary())),
+        5 => FieldValue::LongInt(try!(u.arbitrary())),
+        6 => FieldValue::LongUint(try!(u.arbitrary())),
+        7 => FieldValue::LongLongInt(try!(u.arbitrary())),
+        8 => FieldValue::LongLongUint(try!(u.arbitrary())),
+        // NaN never compares equal, so it can not take part in the round trip.
+        9 => {
+            let v: f32 = try!(u.arbitrary());
+            FieldValue::Float(if v.is_nan() { 0.0 } else { v })
+        },
+        10 => {
+            let v: f64 = try!(u.arbitrary());
+            FieldValue::Double(if v.is_nan() { 0.0 } else { v })
+        },
+        11 => FieldValue::DecimalValue(try!(u.arbitrary()), try!(u.arbitrary())),
+        12 => FieldValue::ShortString(try!(u.arbitrary())),
+        13 => FieldValue::LongString(try!(u.arbitrary())),
+        14 => FieldValue::Timestamp(try!(u.arbitrary())),
+        15 => FieldValue::Void,
+        16 => {
+            let mut array = Vec::new();
+            for _ in 0..try!(u.int_in_range(0..=8)) {
+                array.push(try!(field_value(u, depth + 1)));
+            }
+            FieldValue::FieldArray(array)
+        },
+        _ => FieldValue::FieldTable(try!(table(u, depth + 1))),
+    };
+    Ok(value)
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    let mut start = connection::Start::default();
+    start.server_properties = match table(&mut u, 0) {
+        Ok(table) => table,
+        Err(_) => return,
+    };
+
+    // Names and short strings over 255 bytes are rejected on encoding.
+    let payload = match start.se() {
+        Ok(payload) => payload,
+        Err(_) => return,
+    };
+    let decoded: connection::Start = method::de(&payload).unwrap();
+    assert_eq!(start, decoded);
+});
@@ -0,0 +1,11 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate amqp;
+
+use amqp::method;
+
+fuzz_target!(|data: &[u8]| {
+    let mut data = data;
+    let _ = method::de_table(&mut data);
+});
@@ -0,0 +1,188 @@
+use types::*;
+use types::FieldValue::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueueType {
+    Classic,
+    Quorum,
+    Stream,
+}
+
+impl QueueType {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            QueueType::Classic => "classic",
+            QueueType::Quorum => "quorum",
+            QueueType::Stream => "stream",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overflow {
+    DropHead,
+    RejectPublish,
+    RejectPublishDlx,
+}
+
+impl Overflow {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Overflow::DropHead => "drop-head",
+            Overflow::RejectPublish => "reject-publish",
+            Overflow::RejectPublishDlx => "reject-publish-dlx",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueueArguments {
+    table: Table,
+}
+
+impl QueueArguments {
+    pub fn new() -> QueueArguments {
+        QueueArguments::default()
+    }
+
+    pub fn message_ttl(self, millis: u64) -> QueueArguments {
+        self.int("x-message-ttl", millis)
+    }
+
+    pub fn expires(self, millis: u64) -> QueueArguments {
+        self.int("x-expires", millis)
+    }
+
+    pub fn dead_letter_exchange(self, exchange: &str) -> QueueArguments {
+        self.str("x-dead-letter-exchange", exchange)
+    }
+
+    pub fn dead_letter_routing_key(self, routing_key: &str) -> QueueArguments {
+        self.str("x-dead-letter-routing-key", routing_key)
+    }
+
+    pub fn max_length(self, len: u64) -> QueueArguments {
+        self.int("x-max-length", len)
+    }
+
+    pub fn max_length_bytes(self, bytes: u64) -> QueueArguments {
+        self.int("x-max-length-bytes", bytes)
+    }
+
+    pub fn overflow(self, overflow: Overflow) -> QueueArguments {
+        self.str("x-overflow", overflow.as_str())
+    }
+
+    pub fn max_priority(self, priority: u8) -> QueueArguments {
+        self.int("x-max-priority", priority as u64)
+    }
+
+    pub fn queue_type(self, queue_type: QueueType) -> QueueArguments {
+        self.str("x-queue-type", queue_type.as_str())
+    }
+
+    pub fn single_active_consumer(self, enabled: bool) -> QueueArguments {
+        self.set("x-single-active-consumer", Bool(enabled))
+    }
+
+    pub fn delivery_limit(self, limit: u64) -> QueueArguments {
+        self.int("x-delivery-limit", limit)
+    }
+
+    pub fn max_age(self, age: &str) -> QueueArguments {
+        self.str("x-max-age", age)
+    }
+
+    pub fn stream_max_segment_size_bytes(self, bytes: u64) -> QueueArguments {
+        self.int("x-stream-max-segment-size-bytes", bytes)
+    }
+
+    pub fn set(mut self, key: &str, value: FieldValue) -> QueueArguments {
+        self.table.insert(key.to_string(), value);
+        self
+    }
+
+    pub fn into_table(self) -> Table {
+        self.table
+    }
+
+    fn str(self, key: &str, value: &str) -> QueueArguments {
+        self.set(key, LongString(value.as_bytes().to_vec()))
+    }
+
+    // RabbitMQ reads the 'l' tag, which LongLongUint is written with, as a signed 64-bit
+    // integer, and rejects 'L'; small values use 'I', which every broker understands.
+    // Values past i64::MAX would read back negative, so they are clamped to it.
+    fn int(self, key: &str, value: u64) -> QueueArguments {
+        if value <= i32::MAX as u64 {
+            self.set(key, LongInt(value as i32))
+        } else {
+            self.set(key, LongLongUint(value.min(i64::MAX as u64)))
+        }
+    }
+}
+
+impl From<QueueArguments> for Table {
+    fn from(args: QueueArguments) -> Table {
+        args.into_table()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quorum_dlx() {
+        let table: Table = QueueArguments::new()
+            .queue_type(QueueType::Quorum)
+            .dead_letter_exchange("dlx")
+            .dead_letter_routing_key("dead")
+            .delivery_limit(5)
+            .into();
+
+        assert_eq!(table.len(), 4);
+        assert_eq!(table["x-queue-type"], LongString(b"quorum".to_vec()));
+        assert_eq!(table["x-dead-letter-exchange"], LongString(b"dlx".to_vec()));
+        assert_eq!(table["x-dead-letter-routing-key"], LongString(b"dead".to_vec()));
+        assert_eq!(table["x-delivery-limit"], LongInt(5));
+    }
+
+    #[test]
+    fn test_limits() {
+        let table = QueueArguments::new()
+            .message_ttl(60000)
+            .expires(1 << 40)
+            .max_length(1000)
+            .max_length_bytes(1 << 32)
+            .overflow(Overflow::RejectPublishDlx)
+            .max_priority(10)
+            .single_active_consumer(true)
+            .into_table();
+
+        assert_eq!(table["x-message-ttl"], LongInt(60000));
+        assert_eq!(table["x-expires"], LongLongUint(1 << 40));
+        assert_eq!(table["x-max-length"], LongInt(1000));
+        assert_eq!(table["x-max-length-bytes"], LongLongUint(1 << 32));
+        assert_eq!(table["x-overflow"], LongString(b"reject-publish-dlx".to_vec()));
+        assert_eq!(table["x-max-priority"], LongInt(10));
+        assert_eq!(table["x-single-active-consumer"], Bool(true));
+    }
+
+    #[test]
+    fn test_int_boundary() {
+        let table = QueueArguments::new()
+            .message_ttl(i32::MAX as u64)
+            .expires(i32::MAX as u64 + 1)
+            .max_length(i64::MAX as u64)
+            .max_length_bytes(i64::MAX as u64 + 1)
+            .delivery_limit(u64::MAX)
+            .into_table();
+
+        assert_eq!(table["x-message-ttl"], LongInt(i32::MAX));
+        assert_eq!(table["x-expires"], LongLongUint(i32::MAX as u64 + 1));
+        assert_eq!(table["x-max-length"], LongLongUint(i64::MAX as u64));
+        assert_eq!(table["x-max-length-bytes"], LongLongUint(i64::MAX as u64));
+        assert_eq!(table["x-delivery-limit"], LongLongUint(i64::MAX as u64));
+    }
+}
@@ -0,0 +1,1169 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, FutureExt, TryFutureExt};
+use futures::stream::{Stream, StreamExt};
+use tokio;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{self, TcpStream};
+use tokio::time::{self, Sleep};
+use tokio_util::codec::{Decoder, Encoder};
+
+use std::cmp;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use std::u16;
+
+use channel::CloseReason;
+use compression;
+use conf::Conf;
+use consumer::{Consumer, Delivery, ReturnedMessage};
+use content::{BasicProperties, ContentHeader};
+use engine::{self, Engine, Event, State};
+use frame::{self, Frame};
+use method::{Method, MethodRef};
+use protocol::{self, basic, channel, confirm, exchange, queue};
+use protocol::{BASIC_ACK, BASIC_CANCEL, BASIC_CANCEL_OK, BASIC_CONSUME_OK, BASIC_DELIVER,
+               BASIC_NACK, BASIC_RETURN, CHANNEL_CLOSE, CHANNEL_CLOSE_OK, CHANNEL_FLOW,
+               CONFIRM_SELECT};
+use result::*;
+use types::*;
+
+pub type AmqpFuture<T> = Pin<Box<Future<Output = AmqpResult<T>> + Send>>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Confirmation {
+    NotRequested,
+    Ack,
+    Nack,
+}
+
+#[derive(Debug, Default)]
+pub struct FrameCodec;
+
+impl Decoder for FrameCodec {
+    type Item = Frame;
+    type Error = AmqpError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> AmqpResult<Option<Frame>> {
+        Frame::split_from(src)
+    }
+}
+
+impl Encoder<Frame> for FrameCodec {
+    type Error = AmqpError;
+
+    fn encode(&mut self, frame: Frame, dst: &mut BytesMut) -> AmqpResult<()> {
+        dst.reserve(frame.size());
+        dst.put_u8(frame.ty);
+        dst.put_u16(frame.channel);
+        dst.put_u32(frame.payload.len() as u32);
+        dst.put_slice(&frame.payload);
+        dst.put_u8(protocol::FRAME_END);
+        Ok(())
+    }
+}
+
+type Reply = oneshot::Sender<AmqpResult<Frame>>;
+type DeliverySender = mpsc::UnboundedSender<AmqpResult<Delivery>>;
+type ReturnSender = mpsc::UnboundedSender<ReturnedMessage>;
+
+enum Command {
+    Send(Vec<Frame>),
+    Rpc {
+        channel: u16,
+        frames: Vec<Frame>,
+        reply: Reply,
+        consumer: Option<DeliverySender>,
+    },
+    Publish {
+        channel: u16,
+        frames: Vec<Frame>,
+        confirm: oneshot::Sender<AmqpResult<Confirmation>>,
+    },
+    Returns {
+        channel: u16,
+        sender: ReturnSender,
+    },
+    Close(oneshot::Sender<AmqpResult<()>>),
+}
+
+struct Opened {
+    server_props: Table,
+    channel_max: u16,
+    frame_max: u32,
+}
+
+pub struct AsyncConnection {
+    commands: mpsc::UnboundedSender<Command>,
+    channel_max: u16,
+    frame_max: u32,
+    next_channel: u16,
+
+    pub server_props: Table,
+}
+
+impl AsyncConnection {
+    // Spawns the task that owns the socket, so it has to run inside a tokio runtime.
+    pub fn connect(conf: Conf) -> AmqpFuture<AsyncConnection> {
+        let connected = resolve(conf.host.clone(), conf.port).and_then(|addr| {
+            TcpStream::connect(addr).map_err(AmqpError::IoErr)
+        });
+
+        connected.and_then(move |stream| {
+            let (commands, receiver) = mpsc::unbounded();
+            let (opened, rx) = oneshot::channel();
+            tokio::spawn(Driver::new(stream, Engine::new(conf), receiver, opened));
+            wait_reply(rx).map_ok(move |opened| {
+                AsyncConnection {
+                    commands: commands,
+                    channel_max: opened.channel_max,
+                    frame_max: opened.frame_max,
+                    next_channel: 1,
+
+                    server_props: opened.server_props,
+                }
+            })
+        }).boxed()
+    }
+
+    pub fn open_channel(&mut self) -> AmqpFuture<AsyncChannel> {
+        let channel_max = if self.channel_max == 0 { u16::MAX } else { self.channel_max };
+        if self.next_channel == 0 || self.next_channel > channel_max {
+            return future::err(AmqpError::ChannelMaxErr(channel_max)).boxed();
+        }
+
+        let channel = AsyncChannel {
+            id: self.next_channel,
+            commands: self.commands.clone(),
+            frame_max: self.frame_max,
+            mandatory: false,
+        };
+        self.next_channel = self.next_channel.wrapping_add(1);
+
+        let open: AmqpFuture<channel::OpenOk> = channel.rpc(&channel::Open::default());
+        open.map_ok(move |_| channel).boxed()
+    }
+
+    pub fn close(self) -> AmqpFuture<()> {
+        let (reply, rx) = oneshot::channel();
+        if self.commands.unbounded_send(Command::Close(reply)).is_err() {
+            return future::err(AmqpError::ConnectionClosed).boxed();
+        }
+        wait_reply(rx)
+    }
+}
+
+#[derive(Clone)]
+pub struct AsyncChannel {
+    id: u16,
+    commands: mpsc::UnboundedSender<Command>,
+    frame_max: u32,
+    mandatory: bool,
+}
+
+impl AsyncChannel {
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    // Publishes from this handle ask the broker to return messages it cannot route; they
+    // arrive on returns().
+    pub fn set_mandatory(&mut self, mandatory: bool) {
+        self.mandatory = mandatory;
+    }
+
+    // Messages the broker returned on this channel, from now on. A later call takes them
+    // over and ends the earlier stream.
+    pub fn returns(&self) -> ReturnStream {
+        let (sender, receiver) = mpsc::unbounded();
+        let _ = self.commands.unbounded_send(Command::Returns {
+            channel: self.id,
+            sender: sender,
+        });
+        ReturnStream {
+            receiver: receiver,
+        }
+    }
+
+    pub fn close(self) -> AmqpFuture<()> {
+        let mut close = channel::Close::default();
+        close.reply_code = protocol::REPLY_SUCCESS as u16;
+        close.reply_text = "OK".to_string();
+        let close_ok: AmqpFuture<channel::CloseOk> = self.rpc(&close);
+        close_ok.map_ok(|_| ()).boxed()
+    }
+
+    pub fn confirm_select(&self) -> AmqpFuture<()> {
+        let select_ok: AmqpFuture<confirm::SelectOk> = self.rpc(&confirm::Select::default());
+        select_ok.map_ok(|_| ()).boxed()
+    }
+
+    pub fn queue_declare(&self, declare: &queue::Declare) -> AmqpFuture<queue::DeclareOk> {
+        self.rpc(declare)
+    }
+
+    pub fn queue_bind(&self, bind: &queue::Bind) -> AmqpFuture<()> {
+        let bind_ok: AmqpFuture<queue::BindOk> = self.rpc(bind);
+        bind_ok.map_ok(|_| ()).boxed()
+    }
+
+    pub fn exchange_declare(&self, declare: &exchange::Declare) -> AmqpFuture<()> {
+        let declare_ok: AmqpFuture<exchange::DeclareOk> = self.rpc(declare);
+        declare_ok.map_ok(|_| ()).boxed()
+    }
+
+    pub fn basic_qos(&self, prefetch_size: u32, prefetch_count: u16, global: bool)
+                     -> AmqpFuture<()> {
+        let mut qos = basic::Qos::default();
+        qos.prefetch_size = prefetch_size;
+        qos.prefetch_count = prefetch_count;
+        qos.global = global;
+        let qos_ok: AmqpFuture<basic::QosOk> = self.rpc(&qos);
+        qos_ok.map_ok(|_| ()).boxed()
+    }
+
+    // Resolves once the broker confirms the message when the channel is in confirm mode, and
+    // as soon as it is queued for sending otherwise.
+    pub fn basic_publish(&self, exchange: &str, routing_key: &str, props: &BasicProperties,
+                         body: &[u8]) -> AmqpFuture<Confirmation> {
+        let mut publish = basic::Publish::default();
+        publish.exchange = exchange.to_string();
+        publish.routing_key = routing_key.to_string();
+        publish.mandatory = self.mandatory;
+
+        let frames = match self.content_frames(&publish, props, body) {
+            Ok(frames) => frames,
+            Err(err) => return future::err(err).boxed(),
+        };
+        let (confirm, rx) = oneshot::channel();
+        let command = Command::Publish {
+            channel: self.id,
+            frames: frames,
+            confirm: confirm,
+        };
+        if self.commands.unbounded_send(command).is_err() {
+            return future::err(AmqpError::ConnectionClosed).boxed();
+        }
+        wait_reply(rx)
+    }
+
+    pub fn basic_consume(&self, consumer: &Consumer) -> AmqpFuture<DeliveryStream> {
+        let mut consume = basic::Consume::default();
+        consume.queue = consumer.queue.clone();
+        consume.consumer_tag = consumer.consumer_tag.clone();
+        consume.no_local = consumer.no_local;
+        consume.no_ack = consumer.no_ack;
+        consume.exclusive = consumer.exclusive;
+        consume.arguments = consumer.arguments.clone();
+
+        let (sender, receiver) = mpsc::unbounded();
+        let consume_ok: AmqpFuture<basic::ConsumeOk> =
+            rpc(&self.commands, self.id, &consume, Some(sender));
+        consume_ok.map_ok(move |consume_ok| {
+            DeliveryStream {
+                consumer_tag: consume_ok.consumer_tag,
+                receiver: receiver,
+            }
+        }).boxed()
+    }
+
+    pub fn basic_cancel(&self, consumer_tag: &str) -> AmqpFuture<()> {
+        let mut cancel = basic::Cancel::default();
+        cancel.consumer_tag = consumer_tag.to_string();
+        let cancel_ok: AmqpFuture<basic::CancelOk> = self.rpc(&cancel);
+        cancel_ok.map_ok(|_| ()).boxed()
+    }
+
+    pub fn basic_ack(&self, delivery_tag: u64, multiple: bool) -> AmqpResult<()> {
+        let mut ack = basic::Ack::default();
+        ack.delivery_tag = delivery_tag;
+        ack.multiple = multiple;
+        self.send(&ack)
+    }
+
+    pub fn basic_nack(&self, delivery_tag: u64, multiple: bool, requeue: bool)
+                      -> AmqpResult<()> {
+        let mut nack = basic::Nack::default();
+        nack.delivery_tag = delivery_tag;
+        nack.multiple = multiple;
+        nack.requeue = requeue;
+        self.send(&nack)
+    }
+
+    pub fn basic_reject(&self, delivery_tag: u64, requeue: bool) -> AmqpResult<()> {
+        let mut reject = basic::Reject::default();
+        reject.delivery_tag = delivery_tag;
+        reject.requeue = requeue;
+        self.send(&reject)
+    }
+
+    fn content_frames(&self, publish: &basic::Publish, props: &BasicProperties, body: &[u8])
+                      -> AmqpResult<Vec<Frame>> {
+        let header = ContentHeader::new(body.len() as u64, props.clone());
+        let mut frames = vec![try!(Frame::from_method(self.id, publish)),
+                              try!(Frame::from_content_header(self.id, &header))];
+
+        for chunk in body.chunks(frame::body_frame_max(self.frame_max)) {
+            frames.push(Frame::from_body(self.id, chunk.to_vec()));
+        }
+        Ok(frames)
+    }
+
+    fn send<M: Method>(&self, method: &M) -> AmqpResult<()> {
+        let frame = try!(Frame::from_method(self.id, method));
+        self.commands.unbounded_send(Command::Send(vec![frame]))
+            .map_err(|_| AmqpError::ConnectionClosed)
+    }
+
+    fn rpc<M: Method, R: Method + Send + 'static>(&self, method: &M) -> AmqpFuture<R> {
+        rpc(&self.commands, self.id, method, None)
+    }
+}
+
+pub struct DeliveryStream {
+    consumer_tag: Shortstr,
+    receiver: mpsc::UnboundedReceiver<AmqpResult<Delivery>>,
+}
+
+impl DeliveryStream {
+    pub fn consumer_tag(&self) -> &str {
+        &self.consumer_tag
+    }
+}
+
+impl Stream for DeliveryStream {
+    type Item = AmqpResult<Delivery>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+                 -> Poll<Option<AmqpResult<Delivery>>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+pub struct ReturnStream {
+    receiver: mpsc::UnboundedReceiver<ReturnedMessage>,
+}
+
+impl Stream for ReturnStream {
+    type Item = ReturnedMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<ReturnedMessage>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+fn rpc<M: Method, R: Method + Send + 'static>(commands: &mpsc::UnboundedSender<Command>,
+                                              channel: u16, method: &M,
+                                              consumer: Option<DeliverySender>)
+                                              -> AmqpFuture<R> {
+    let frame = match Frame::from_method(channel, method) {
+        Ok(frame) => frame,
+        Err(err) => return future::err(err).boxed(),
+    };
+    let (reply, rx) = oneshot::channel();
+    let command = Command::Rpc {
+        channel: channel,
+        frames: vec![frame],
+        reply: reply,
+        consumer: consumer,
+    };
+    if commands.unbounded_send(command).is_err() {
+        return future::err(AmqpError::ConnectionClosed).boxed();
+    }
+    wait_reply(rx).and_then(|frame| future::ready(frame.to_method())).boxed()
+}
+
+// The driver drops a reply only when the connection is gone.
+fn wait_reply<T: Send + 'static>(rx: oneshot::Receiver<AmqpResult<T>>) -> AmqpFuture<T> {
+    rx.map(|result| result.unwrap_or_else(|_| Err(AmqpError::ConnectionClosed))).boxed()
+}
+
+// lookup_host runs the blocking name lookup on tokio's blocking pool, off the executor.
+fn resolve(host: String, port: u16) -> AmqpFuture<SocketAddr> {
+    net::lookup_host((host, port)).map(|result| {
+        match result {
+            Ok(mut addrs) => addrs.next().ok_or_else(|| {
+                AmqpError::IoErr(io::Error::new(io::ErrorKind::NotFound, "no address for host"))
+            }),
+            Err(err) => Err(AmqpError::IoErr(err)),
+        }
+    }).boxed()
+}
+
+struct Waiter {
+    reply: Reply,
+    consumer: Option<DeliverySender>,
+}
+
+struct PendingContent {
+    // The basic.deliver or basic.return frame, only decoded once the content is complete and
+    // has somewhere to go.
+    method: Frame,
+    header: Option<ContentHeader>,
+    body: Vec<Bytes>,
+    body_size: u64,
+}
+
+#[derive(Default)]
+struct ChannelState {
+    waiters: VecDeque<Waiter>,
+    consumers: HashMap<Shortstr, DeliverySender>,
+    returns: Option<ReturnSender>,
+    content: Option<PendingContent>,
+    confirms: Option<Confirms>,
+    // Publishes held back while the broker has the channel paused with channel.flow.
+    paused: bool,
+    held: VecDeque<Frame>,
+}
+
+#[derive(Default)]
+struct Confirms {
+    next_tag: u64,
+    pending: BTreeMap<u64, oneshot::Sender<AmqpResult<Confirmation>>>,
+}
+
+struct Driver {
+    stream: TcpStream,
+    engine: Engine,
+    input: Vec<u8>,
+    output: BytesMut,
+    timer: Option<Pin<Box<Sleep>>>,
+    commands: mpsc::UnboundedReceiver<Command>,
+    channels: HashMap<u16, ChannelState>,
+    opened: Option<oneshot::Sender<AmqpResult<Opened>>>,
+    closing: Option<oneshot::Sender<AmqpResult<()>>>,
+    closed: bool,
+}
+
+// The driver only moves bytes between the socket and the engine, which runs the handshake,
+// heartbeats and connection close, and routes the channel frames the engine hands back.
+impl Driver {
+    fn new(stream: TcpStream, mut engine: Engine, commands: mpsc::UnboundedReceiver<Command>,
+           opened: oneshot::Sender<AmqpResult<Opened>>)
+           -> Driver {
+        engine.start(Instant::now());
+        Driver {
+            stream: stream,
+            engine: engine,
+            input: vec![0u8; engine::INPUT_CHUNK],
+            output: BytesMut::new(),
+            timer: None,
+            commands: commands,
+            channels: HashMap::new(),
+            opened: Some(opened),
+            closing: None,
+            closed: false,
+        }
+    }
+
+    fn poll_commands(&mut self, cx: &mut Context) {
+        loop {
+            match self.commands.poll_next_unpin(cx) {
+                Poll::Ready(Some(command)) => self.handle_command(command),
+                Poll::Ready(None) => {
+                    self.closed = true;
+                    return;
+                },
+                Poll::Pending => return,
+            }
+        }
+    }
+
+    fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::Send(frames) => send_frames(&mut self.engine, frames),
+            Command::Rpc { channel, frames, reply, consumer } => {
+                let state = self.channels.entry(channel).or_insert_with(ChannelState::default);
+                if frames.first().map_or(false, |f| f.method_id().ok() == Some(CONFIRM_SELECT)) {
+                    state.confirms.get_or_insert_with(Confirms::default);
+                }
+                state.waiters.push_back(Waiter {
+                    reply: reply,
+                    consumer: consumer,
+                });
+                send_frames(&mut self.engine, frames);
+            },
+            Command::Publish { channel, frames, confirm } => {
+                let state = self.channels.entry(channel).or_insert_with(ChannelState::default);
+                match state.confirms {
+                    Some(ref mut confirms) => {
+                        confirms.next_tag += 1;
+                        confirms.pending.insert(confirms.next_tag, confirm);
+                    },
+                    None => {
+                        let _ = confirm.send(Ok(Confirmation::NotRequested));
+                    },
+                }
+                if state.paused {
+                    state.held.extend(frames);
+                } else {
+                    send_frames(&mut self.engine, frames);
+                }
+            },
+            Command::Returns { channel, sender } => {
+                let state = self.channels.entry(channel).or_insert_with(ChannelState::default);
+                state.returns = Some(sender);
+            },
+            Command::Close(reply) => {
+                match self.engine.close() {
+                    Ok(()) if self.engine.state() == State::Closing => self.closing = Some(reply),
+                    result => {
+                        let _ = reply.send(result);
+                    },
+                }
+            },
+        }
+    }
+
+    fn poll_recv(&mut self, cx: &mut Context) -> AmqpResult<()> {
+        loop {
+            let n = {
+                let mut buf = ReadBuf::new(&mut self.input);
+                match Pin::new(&mut self.stream).poll_read(cx, &mut buf) {
+                    Poll::Ready(result) => try!(result),
+                    Poll::Pending => return Ok(()),
+                }
+                buf.filled().len()
+            };
+            if n == 0 {
+                return Err(AmqpError::ConnectionClosed);
+            }
+            let result = self.engine.handle_input(&self.input[..n], Instant::now());
+            try!(self.poll_events());
+            try!(result);
+        }
+    }
+
+    // Sends a heartbeat once the connection has been quiet for an interval, and fails it once
+    // the broker has been silent for two.
+    fn poll_timer(&mut self, cx: &mut Context) -> AmqpResult<()> {
+        loop {
+            let deadline = match self.engine.poll_timeout() {
+                Some(deadline) => time::Instant::from_std(deadline),
+                None => {
+                    self.timer = None;
+                    return Ok(());
+                },
+            };
+            {
+                let timer = self.timer.get_or_insert_with(|| {
+                    Box::pin(time::sleep_until(deadline))
+                });
+                if timer.deadline() != deadline {
+                    timer.as_mut().reset(deadline);
+                }
+                if timer.as_mut().poll(cx).is_pending() {
+                    return Ok(());
+                }
+            }
+
+            let now = Instant::now();
+            let result = self.engine.handle_timeout(now);
+            try!(self.poll_events());
+            try!(result);
+            // Taking the heartbeat moves the send deadline on.
+            self.take_output(now);
+        }
+    }
+
+    fn poll_send(&mut self, cx: &mut Context) -> AmqpResult<()> {
+        self.take_output(Instant::now());
+        while !self.output.is_empty() {
+            match Pin::new(&mut self.stream).poll_write(cx, &self.output) {
+                Poll::Ready(Ok(0)) => {
+                    let err = io::Error::new(io::ErrorKind::WriteZero, "socket closed");
+                    return Err(AmqpError::IoErr(err));
+                },
+                Poll::Ready(Ok(n)) => {
+                    let _ = self.output.split_to(n);
+                },
+                Poll::Ready(Err(err)) => return Err(AmqpError::IoErr(err)),
+                Poll::Pending => return Ok(()),
+            }
+        }
+        Ok(())
+    }
+
+    fn take_output(&mut self, now: Instant) {
+        if let Some(data) = self.engine.poll_transmit(now) {
+            self.output.extend_from_slice(&data);
+        }
+    }
+
+    fn poll_events(&mut self) -> AmqpResult<()> {
+        while let Some(event) = self.engine.poll_event() {
+            match event {
+                Event::Opened => {
+                    if let Some(opened) = self.opened.take() {
+                        let _ = opened.send(Ok(Opened {
+                            server_props: self.engine.server_props().clone(),
+                            channel_max: self.engine.channel_max(),
+                            frame_max: self.engine.frame_max(),
+                        }));
+                    }
+                },
+                Event::Closed(_) => {
+                    self.fail_all(|| AmqpError::ConnectionClosed);
+                    if let Some(closing) = self.closing.take() {
+                        let _ = closing.send(Ok(()));
+                    }
+                },
+                Event::Frame(frame) => try!(self.dispatch(frame)),
+            }
+        }
+        Ok(())
+    }
+
+    fn dispatch(&mut self, frame: Frame) -> AmqpResult<()> {
+        match frame.ty {
+            protocol::FRAME_METHOD => self.dispatch_method(frame),
+            protocol::FRAME_HEADER | protocol::FRAME_BODY => self.dispatch_content(frame),
+            ty => Err(AmqpError::FrameTypeErr(ty, protocol::FRAME_METHOD)),
+        }
+    }
+
+    fn dispatch_method(&mut self, frame: Frame) -> AmqpResult<()> {
+        let id = frame.channel;
+        let method_id = try!(frame.method_id());
+        match method_id {
+            CHANNEL_FLOW => {
+                let flow: channel::Flow = try!(frame.to_method());
+                let mut flow_ok = channel::FlowOk::default();
+                flow_ok.active = flow.active;
+                try!(self.engine.send_method(id, &flow_ok));
+
+                let state = self.channels.entry(id).or_insert_with(ChannelState::default);
+                state.paused = !flow.active;
+                if flow.active {
+                    send_frames(&mut self.engine, state.held.drain(..));
+                }
+            },
+            CHANNEL_CLOSE => {
+                let close: channel::Close = try!(frame.to_method());
+                try!(self.engine.send_method(id, &channel::CloseOk::default()));
+                let reason = CloseReason {
+                    reply_code: close.reply_code,
+                    reply_text: close.reply_text,
+                    class_id: close.class_id,
+                    method_id: close.method_id,
+                };
+                if let Some(state) = self.channels.remove(&id) {
+                    state.fail(|| closed_err(&reason));
+                }
+            },
+            CHANNEL_CLOSE_OK => {
+                self.reply(id, frame);
+                self.channels.remove(&id);
+            },
+            BASIC_CANCEL => {
+                let cancel: basic::Cancel = try!(frame.to_method());
+                let consumer = self.channels.get_mut(&id)
+                                   .and_then(|state| state.consumers.remove(&cancel.consumer_tag));
+                if let Some(consumer) = consumer {
+                    let err = AmqpError::ConsumerCancelled(cancel.consumer_tag.clone());
+                    let _ = consumer.unbounded_send(Err(err));
+                }
+                if !cancel.nowait {
+                    let mut cancel_ok = basic::CancelOk::default();
+                    cancel_ok.consumer_tag = cancel.consumer_tag;
+                    try!(self.engine.send_method(id, &cancel_ok));
+                }
+            },
+            BASIC_DELIVER | BASIC_RETURN => {
+                let state = self.channels.entry(id).or_insert_with(ChannelState::default);
+                state.content = Some(PendingContent {
+                    method: frame,
+                    header: None,
+                    body: Vec::new(),
+                    body_size: 0,
+                });
+            },
+            BASIC_ACK | BASIC_NACK => {
+                let (delivery_tag, multiple, confirmation) = if method_id == BASIC_ACK {
+                    let ack: basic::Ack = try!(frame.to_method());
+                    (ack.delivery_tag, ack.multiple, Confirmation::Ack)
+                } else {
+                    let nack: basic::Nack = try!(frame.to_method());
+                    (nack.delivery_tag, nack.multiple, Confirmation::Nack)
+                };
+                if let Some(confirms) = self.channels.get_mut(&id)
+                                                     .and_then(|s| s.confirms.as_mut()) {
+                    confirms.settle(delivery_tag, multiple, confirmation);
+                }
+            },
+            _ => self.reply(id, frame),
+        }
+        Ok(())
+    }
+
+    fn dispatch_content(&mut self, frame: Frame) -> AmqpResult<()> {
+        let state = match self.channels.get_mut(&frame.channel) {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        // A body is buffered up to the limit a decompressed one gets, and a larger one closes
+        // the channel rather than the connection.
+        let max_size = compression::DEFAULT_MAX_SIZE;
+        let (done, too_large) = match state.content {
+            Some(ref mut content) => {
+                if frame.ty == protocol::FRAME_HEADER {
+                    content.header = Some(try!(frame.to_content_header()));
+                } else {
+                    content.body_size += frame.payload.len() as u64;
+                    content.body.push(frame.payload);
+                }
+                let body_size = content.body_size;
+                let declared = content.header.as_ref().map_or(0, |h| h.body_size);
+                (content.header.is_some() && body_size >= declared,
+                 cmp::max(body_size, declared) > max_size as u64)
+            },
+            None => return Err(AmqpError::FrameTypeErr(frame.ty, protocol::FRAME_METHOD)),
+        };
+        if too_large {
+            let content = state.content.take().unwrap();
+            let method_id = try!(content.method.method_id());
+            return self.close_channel(frame.channel, method_id, || {
+                AmqpError::BodySizeErr(max_size)
+            });
+        }
+        if !done {
+            return Ok(());
+        }
+
+        let content = state.content.take().unwrap();
+        let properties = content.header.unwrap().properties;
+        if try!(content.method.method_id()) == BASIC_RETURN {
+            let returns = match state.returns {
+                Some(ref returns) => returns,
+                None => return Ok(()),
+            };
+            let ret: basic::Return = try!(content.method.to_method());
+            let _ = returns.unbounded_send(ReturnedMessage {
+                reply_code: ret.reply_code,
+                reply_text: ret.reply_text,
+                exchange: ret.exchange,
+                routing_key: ret.routing_key,
+                properties: properties,
+                body: frame::join_body(content.body),
+            });
+            return Ok(());
+        }
+
+        let deliver = try!(basic::DeliverRef::decode(&content.method.payload));
+        let consumer = match state.consumers.get(deliver.consumer_tag) {
+            Some(consumer) => consumer,
+            None => return Ok(()),
+        };
+        let mut delivery = Delivery {
+            consumer_tag: deliver.consumer_tag.to_string(),
+            delivery_tag: deliver.delivery_tag,
+            redelivered: deliver.redelivered,
+            exchange: deliver.exchange.to_string(),
+            routing_key: deliver.routing_key.to_string(),
+            properties: properties,
+            body: frame::join_body(content.body),
+        };
+        // A body that fails to decompress is delivered untouched, see Delivery.
+        let _ = delivery.decompress(compression::DEFAULT_MAX_SIZE);
+        let _ = consumer.unbounded_send(Ok(delivery));
+        Ok(())
+    }
+
+    fn close_channel<F: Fn() -> AmqpError>(&mut self, id: u16, method_id: (u16, u16), err: F)
+                                           -> AmqpResult<()> {
+        let mut close = channel::Close::default();
+        close.reply_code = protocol::RESOURCE_ERROR;
+        close.reply_text = err().to_string();
+        close.class_id = method_id.0;
+        close.method_id = method_id.1;
+        try!(self.engine.send_method(id, &close));
+        if let Some(state) = self.channels.remove(&id) {
+            state.fail(err);
+        }
+        Ok(())
+    }
+
+    fn reply(&mut self, channel: u16, frame: Frame) {
+        let state = match self.channels.get_mut(&channel) {
+            Some(state) => state,
+            None => return,
+        };
+        let waiter = match state.waiters.pop_front() {
+            Some(waiter) => waiter,
+            None => return,
+        };
+
+        if let Some(consumer) = waiter.consumer {
+            if frame.method_id().ok() == Some(BASIC_CONSUME_OK) {
+                if let Ok(consume_ok) = frame.to_method::<basic::ConsumeOk>() {
+                    state.consumers.insert(consume_ok.consumer_tag, consumer);
+                }
+            }
+        }
+        if frame.method_id().ok() == Some(BASIC_CANCEL_OK) {
+            if let Ok(cancel_ok) = frame.to_method::<basic::CancelOk>() {
+                state.consumers.remove(&cancel_ok.consumer_tag);
+            }
+        }
+        let _ = waiter.reply.send(Ok(frame));
+    }
+
+    fn fail_all<F: Fn() -> AmqpError>(&mut self, err: F) {
+        for (_, state) in self.channels.drain() {
+            state.fail(&err);
+        }
+    }
+}
+
+impl ChannelState {
+    fn fail<F: Fn() -> AmqpError>(self, err: F) {
+        for waiter in self.waiters {
+            let _ = waiter.reply.send(Err(err()));
+        }
+        for (_, consumer) in self.consumers {
+            let _ = consumer.unbounded_send(Err(err()));
+        }
+        if let Some(confirms) = self.confirms {
+            for (_, confirm) in confirms.pending {
+                let _ = confirm.send(Err(err()));
+            }
+        }
+    }
+}
+
+impl Confirms {
+    fn settle(&mut self, delivery_tag: u64, multiple: bool, confirmation: Confirmation) {
+        if multiple {
+            let rest = match delivery_tag.checked_add(1) {
+                Some(next) => self.pending.split_off(&next),
+                None => BTreeMap::new(),
+            };
+            for (_, confirm) in ::std::mem::replace(&mut self.pending, rest) {
+                let _ = confirm.send(Ok(confirmation));
+            }
+        } else if let Some(confirm) = self.pending.remove(&delivery_tag) {
+            let _ = confirm.send(Ok(confirmation));
+        }
+    }
+}
+
+impl Future for Driver {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let this = &mut *self;
+        this.poll_commands(cx);
+        let result = this.poll_recv(cx)
+                         .and_then(|_| this.poll_timer(cx))
+                         .and_then(|_| this.poll_send(cx));
+        match result {
+            Ok(()) if !this.output.is_empty() => Poll::Pending,
+            Ok(()) if this.closed || this.engine.state() == State::Closed => Poll::Ready(()),
+            Ok(()) => Poll::Pending,
+            Err(err) => {
+                // Best effort, for a close the engine queued on the way out.
+                let _ = this.poll_send(cx);
+                if let Some(opened) = this.opened.take() {
+                    let _ = opened.send(Err(err));
+                }
+                if let Some(closing) = this.closing.take() {
+                    let _ = closing.send(Err(AmqpError::ConnectionClosed));
+                }
+                this.fail_all(|| AmqpError::ConnectionClosed);
+                Poll::Ready(())
+            },
+        }
+    }
+}
+
+fn send_frames<I: IntoIterator<Item = Frame>>(engine: &mut Engine, frames: I) {
+    for frame in frames {
+        engine.send_frame(&frame);
+    }
+}
+
+fn closed_err(reason: &CloseReason) -> AmqpError {
+    AmqpError::ChannelClosed {
+        reply_code: reason.reply_code,
+        reply_text: reason.reply_text.clone(),
+        class_id: reason.class_id,
+        method_id: reason.method_id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::connection;
+    use futures::executor;
+    use futures::stream::TryStreamExt;
+    use socket::Peer;
+    use tokio::runtime::Runtime;
+
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::sync::mpsc as std_mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    // Plays the broker through the handshake and channel 1's open, then runs the script.
+    fn broker<F>(script: F) -> (Conf, thread::JoinHandle<()>)
+        where F: FnOnce(::std::net::TcpStream) + Send + 'static
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut conf = Conf::default();
+        conf.host = "127.0.0.1".to_string();
+        conf.port = listener.local_addr().unwrap().port();
+
+        let handle = thread::spawn(move || {
+            let (mut server, _) = listener.accept().unwrap();
+            let mut header = [0u8; 8];
+            server.read_exact(&mut header).unwrap();
+            assert_eq!(&header[..], protocol::PROT_HEADER);
+            server.write_method(0, &connection::Start::default());
+            server.read_frame();
+            server.write_method(0, &connection::Tune::default());
+            server.read_frame();
+            server.read_frame();
+            server.write_method(0, &connection::OpenOk::default());
+            server.read_frame();
+            server.write_method(1, &channel::OpenOk::default());
+            script(server);
+        });
+        (conf, handle)
+    }
+
+    #[test]
+    fn test_codec() {
+        let mut codec = FrameCodec;
+        let mut buf = BytesMut::new();
+        let frame = Frame::from_method(1, &channel::Open::default()).unwrap();
+        codec.encode(frame, &mut buf).unwrap();
+        codec.encode(Frame::heartbeat(), &mut buf).unwrap();
+
+        let mut partial = buf.split_to(5);
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+        partial.unsplit(buf);
+
+        let frame = codec.decode(&mut partial).unwrap().unwrap();
+        assert_eq!(frame.channel, 1);
+        assert_eq!(frame.method_id().unwrap(), (20, 10));
+        let frame = codec.decode(&mut partial).unwrap().unwrap();
+        assert_eq!(frame.ty, protocol::FRAME_HEARTBEAT);
+        assert!(partial.is_empty());
+    }
+
+    #[test]
+    fn test_confirms() {
+        let mut confirms = Confirms::default();
+        let mut receivers = Vec::new();
+        for _ in 0..3 {
+            let (tx, rx) = oneshot::channel();
+            confirms.next_tag += 1;
+            confirms.pending.insert(confirms.next_tag, tx);
+            receivers.push(rx);
+        }
+
+        confirms.settle(2, true, Confirmation::Ack);
+        confirms.settle(3, false, Confirmation::Nack);
+        let results: Vec<_> = receivers.into_iter()
+            .map(|rx| executor::block_on(rx).unwrap().unwrap())
+            .collect();
+        assert_eq!(results, vec![Confirmation::Ack, Confirmation::Ack, Confirmation::Nack]);
+        assert!(confirms.pending.is_empty());
+    }
+
+    #[test]
+    fn test_confirms_max_tag() {
+        let mut confirms = Confirms::default();
+        let (tx, rx) = oneshot::channel();
+        confirms.pending.insert(u64::MAX, tx);
+
+        confirms.settle(u64::MAX, true, Confirmation::Ack);
+        assert_eq!(executor::block_on(rx).unwrap().unwrap(), Confirmation::Ack);
+        assert!(confirms.pending.is_empty());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let rt = Runtime::new().unwrap();
+        let addr = rt.block_on(resolve("localhost".to_string(), 5672)).unwrap();
+        assert!(addr.ip().is_loopback());
+        assert_eq!(addr.port(), 5672);
+    }
+
+    #[test]
+    fn test_close() {
+        let (conf, broker) = broker(|mut server| {
+            let close: connection::Close = server.read_frame().to_method().unwrap();
+            assert_eq!(close.reply_code, protocol::REPLY_SUCCESS as u16);
+            server.write_method(0, &connection::CloseOk::default());
+        });
+
+        let rt = Runtime::new().unwrap();
+        let mut conn = rt.block_on(AsyncConnection::connect(conf)).unwrap();
+        let channel = rt.block_on(conn.open_channel()).unwrap();
+        rt.block_on(conn.close()).unwrap();
+        match rt.block_on(channel.queue_declare(&queue::Declare::default())) {
+            Err(AmqpError::ConnectionClosed) => (),
+            _ => panic!("expected the channel to be closed with the connection"),
+        }
+        broker.join().unwrap();
+    }
+
+    #[test]
+    fn test_deliver() {
+        let (conf, broker) = broker(|mut server| {
+            assert_eq!(server.read_frame().method_id().unwrap(), protocol::BASIC_CONSUME);
+            let mut consume_ok = basic::ConsumeOk::default();
+            consume_ok.consumer_tag = "ctag".to_string();
+            server.write_method(1, &consume_ok);
+
+            let mut deliver = basic::Deliver::default();
+            deliver.consumer_tag = "ctag".to_string();
+            deliver.delivery_tag = 7;
+            deliver.routing_key = "q".to_string();
+            server.write_method(1, &deliver);
+            server.write_content(1, &BasicProperties::default(), b"hello");
+        });
+
+        let rt = Runtime::new().unwrap();
+        let mut conn = rt.block_on(AsyncConnection::connect(conf)).unwrap();
+        let channel = rt.block_on(conn.open_channel()).unwrap();
+        let deliveries = rt.block_on(channel.basic_consume(&Consumer::new("q"))).unwrap();
+        let (delivery, _) = rt.block_on(deliveries.into_future());
+        let delivery = delivery.unwrap().unwrap();
+        assert_eq!(delivery.consumer_tag, "ctag");
+        assert_eq!(delivery.delivery_tag, 7);
+        assert_eq!(delivery.routing_key, "q");
+        assert_eq!(delivery.body, &b"hello"[..]);
+        broker.join().unwrap();
+    }
+
+    #[test]
+    fn test_body_too_large() {
+        let (conf, broker) = broker(|mut server| {
+            assert_eq!(server.read_frame().method_id().unwrap(), protocol::BASIC_CONSUME);
+            let mut consume_ok = basic::ConsumeOk::default();
+            consume_ok.consumer_tag = "ctag".to_string();
+            server.write_method(1, &consume_ok);
+
+            let mut deliver = basic::Deliver::default();
+            deliver.consumer_tag = "ctag".to_string();
+            server.write_method(1, &deliver);
+            let size = compression::DEFAULT_MAX_SIZE as u64 + 1;
+            let header = ContentHeader::new(size, BasicProperties::default());
+            server.write_frame(protocol::FRAME_HEADER, 1, &header.se().unwrap());
+
+            let close: channel::Close = server.read_frame().to_method().unwrap();
+            assert_eq!(close.reply_code, protocol::RESOURCE_ERROR);
+            assert_eq!((close.class_id, close.method_id), protocol::BASIC_DELIVER);
+        });
+
+        let rt = Runtime::new().unwrap();
+        let mut conn = rt.block_on(AsyncConnection::connect(conf)).unwrap();
+        let channel = rt.block_on(conn.open_channel()).unwrap();
+        let deliveries = rt.block_on(channel.basic_consume(&Consumer::new("q"))).unwrap();
+        match rt.block_on(deliveries.try_collect::<Vec<_>>()) {
+            Err(AmqpError::BodySizeErr(max)) => assert_eq!(max, compression::DEFAULT_MAX_SIZE),
+            _ => panic!("expected the oversized body to fail the consumer"),
+        }
+        broker.join().unwrap();
+    }
+
+    #[test]
+    fn test_flow() {
+        let (paused_tx, paused_rx) = std_mpsc::channel();
+        let (published_tx, published_rx) = std_mpsc::channel();
+        let (conf, broker) = broker(move |mut server| {
+            let mut flow = channel::Flow::default();
+            server.write_method(1, &flow);
+            assert_eq!(server.read_frame().method_id().unwrap(), protocol::CHANNEL_FLOW_OK);
+            paused_tx.send(()).unwrap();
+
+            // The publish is queued by now but must not reach the wire while paused.
+            published_rx.recv().unwrap();
+            server.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+            assert!(server.read(&mut [0u8; 1]).is_err());
+            server.set_read_timeout(None).unwrap();
+
+            flow.active = true;
+            server.write_method(1, &flow);
+            assert_eq!(server.read_frame().method_id().unwrap(), protocol::CHANNEL_FLOW_OK);
+            assert_eq!(server.read_frame().method_id().unwrap(), protocol::BASIC_PUBLISH);
+            assert_eq!(server.read_frame().ty, protocol::FRAME_HEADER);
+            assert_eq!(&server.read_frame().payload[..], b"held");
+        });
+
+        let rt = Runtime::new().unwrap();
+        let mut conn = rt.block_on(AsyncConnection::connect(conf)).unwrap();
+        let channel = rt.block_on(conn.open_channel()).unwrap();
+        paused_rx.recv().unwrap();
+        let publish = channel.basic_publish("", "q", &BasicProperties::default(), b"held");
+        assert_eq!(rt.block_on(publish).unwrap(), Confirmation::NotRequested);
+        published_tx.send(()).unwrap();
+        broker.join().unwrap();
+    }
+
+    #[test]
+    fn test_return() {
+        let (conf, broker) = broker(|mut server| {
+            let publish: basic::Publish = server.read_frame().to_method().unwrap();
+            assert!(publish.mandatory);
+            assert_eq!(server.read_frame().ty, protocol::FRAME_HEADER);
+            assert_eq!(&server.read_frame().payload[..], b"lost");
+
+            let mut ret = basic::Return::default();
+            ret.reply_code = protocol::NO_ROUTE;
+            ret.reply_text = "NO_ROUTE".to_string();
+            ret.routing_key = "nowhere".to_string();
+            server.write_method(1, &ret);
+            server.write_content(1, &BasicProperties::default(), b"lost");
+        });
+
+        let rt = Runtime::new().unwrap();
+        let mut conn = rt.block_on(AsyncConnection::connect(conf)).unwrap();
+        let mut channel = rt.block_on(conn.open_channel()).unwrap();
+        channel.set_mandatory(true);
+        let returns = channel.returns();
+        let publish = channel.basic_publish("", "nowhere", &BasicProperties::default(), b"lost");
+        assert_eq!(rt.block_on(publish).unwrap(), Confirmation::NotRequested);
+
+        let (returned, _) = rt.block_on(returns.into_future());
+        let returned = returned.unwrap();
+        assert_eq!(returned.reply_code, protocol::NO_ROUTE);
+        assert_eq!(returned.routing_key, "nowhere");
+        assert_eq!(returned.body, &b"lost"[..]);
+        broker.join().unwrap();
+    }
+
+    #[test]
+    fn test_server_cancel() {
+        let (conf, broker) = broker(|mut server| {
+            assert_eq!(server.read_frame().method_id().unwrap(), protocol::BASIC_CONSUME);
+            let mut consume_ok = basic::ConsumeOk::default();
+            consume_ok.consumer_tag = "ctag".to_string();
+            server.write_method(1, &consume_ok);
+
+            let mut cancel = basic::Cancel::default();
+            cancel.consumer_tag = "ctag".to_string();
+            cancel.nowait = true;
+            server.write_method(1, &cancel);
+        });
+
+        let rt = Runtime::new().unwrap();
+        let mut conn = rt.block_on(AsyncConnection::connect(conf)).unwrap();
+        let channel = rt.block_on(conn.open_channel()).unwrap();
+        let deliveries = rt.block_on(channel.basic_consume(&Consumer::new("q"))).unwrap();
+        match rt.block_on(deliveries.try_collect::<Vec<_>>()) {
+            Err(AmqpError::ConsumerCancelled(tag)) => assert_eq!(tag, "ctag"),
+            _ => panic!("expected the cancel to end the stream with an error"),
+        }
+        broker.join().unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-extern crate rustc_serialize as sede;
+extern crate serde_json;
 extern crate zadt;
 #[macro_use]
 extern crate zbase;
@@ -10,12 +10,20 @@ use std::io::Write;
 use std::process;
 use std::u8;
 
-use sede::json::Json;
+use serde_json::Value;
 
 use zadt::zstr;
 use zbase::zenv;
 use zbase::zproc;
 
+const USES: &'static str = r#"
+use byteorder::{BigEndian, ReadBytesExt};
+
+use method;
+use result::*;
+use types::Short;
+"#;
+
 const HEADER_STRUCT: &'static str = r#"
 #[derive(Debug)]
 pub struct Header {
@@ -51,16 +59,20 @@ pub const {}: u8 = {};"#);
     (CONST_U16) => (r#"
 pub const {}: u16 = {};"#);
 
+    (CONST_METHOD_ID) => (r#"
+pub const {}: (Short, Short) = ({}, {});"#);
+
     (CLASS_MOD) => (r#"
 pub mod {} {{
-    use method::Method;
+    use method::{{{}}};
+    use result::*;
     use types::*;
     {}
 }}
 "#);
 
     (METHOD_STRUCT) => (r#"
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct {} {{
         cid: Short,
         id: Short,{}
@@ -71,22 +83,45 @@ pub mod {} {{
         pub {}: {},"#);
 
     (METHOD_IMPL) => (r#"
-    impl Method for {} {{{}
+    impl Method for {} {{
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {{
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);{}
+            Ok(())
+        }}
+
+        fn decode(data: &[u8]) -> AmqpResult<{}> {{
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));{}
+            try!(reader.finish());
+            Ok({} {{
+                cid: cid,
+                id: id,{}
+            }})
+        }}
     }}
 "#);
 
-    (METHOD_IMPL_STR_TYPE) => (r#"
-        fn str_type(param: &str) -> Option<StrType> {{
-            match param {{ {}
-                _ => None
-            }}
-        }}"#);
-
-    (STR_TYPE_SHORT_STR) => (r#"
-                "{}" => Some(StrType::Short),"#);
-
-    (STR_TYPE_LONG_STR) => (r#"
-                "{}" => Some(StrType::Long),"#);
+    (ENCODE_FIELD) => (r#"
+            writer.write_{}(self.{});"#);
+    (ENCODE_FIELD_REF) => (r#"
+            writer.write_{}(&self.{});"#);
+    (ENCODE_FIELD_TRY) => (r#"
+            try!(writer.write_{}(&self.{}));"#);
+    (ENCODE_BITS) => (r#"
+            writer.write_bits(&[{}]);"#);
+
+    (DECODE_FIELD) => (r#"
+            let {} = try!(reader.read_{}("{}"));"#);
+    (DECODE_BITS) => (r#"
+            let bits = try!(reader.read_octet("{}"));"#);
+    (DECODE_BIT) => (r#"
+            let {} = bits & 0x{:02x} != 0;"#);
+
+    (METHOD_FIELD) => (r#"
+                {}: {},"#);
 
     (METHOD_DEFAULT) => (r#"
     impl Default for {} {{
@@ -101,6 +136,84 @@ pub mod {} {{
 
     (METHOD_DEFAULT_FIELD) => (r#"
                 {}: {},"#);
+
+    (METHOD_REF) => (r#"
+    #[derive(Debug, PartialEq)]
+    pub struct {}Ref<'a> {{
+        cid: Short,
+        id: Short,{}
+    }}
+
+    impl<'a> MethodRef<'a> for {}Ref<'a> {{
+        type Owned = {};
+
+        fn decode(data: &'a [u8]) -> AmqpResult<{}Ref<'a>> {{
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));{}
+            try!(reader.finish());
+            Ok({}Ref {{
+                cid: cid,
+                id: id,{}
+            }})
+        }}
+
+        fn into_owned(self) -> {} {{
+            {} {{
+                cid: self.cid,
+                id: self.id,{}
+            }}
+        }}
+    }}
+"#);
+
+    (METHOD_REF_OWNED_FIELD) => (r#"
+                {}: self.{}{},"#);
+
+    (METHOD_ENUM) => (r#"
+#[derive(Debug, PartialEq)]
+pub enum AmqpMethod {{{}
+}}
+
+impl AmqpMethod {{
+    pub fn decode(payload: &[u8]) -> AmqpResult<AmqpMethod> {{
+        let mut ids = payload;
+        let class_id = try!(ids.read_u16::<BigEndian>());
+        let method_id = try!(ids.read_u16::<BigEndian>());
+        let method = match (class_id, method_id) {{{}
+            _ => return Err(AmqpError::UnknownMethod(class_id, method_id)),
+        }};
+        Ok(method)
+    }}
+
+    pub fn class_id(&self) -> Short {{
+        match *self {{{}
+        }}
+    }}
+
+    pub fn method_id(&self) -> Short {{
+        match *self {{{}
+        }}
+    }}
+
+    pub fn name(&self) -> &'static str {{
+        match *self {{{}
+        }}
+    }}
+}}
+"#);
+
+    (METHOD_ENUM_VARIANT) => (r#"
+    {}({}::{}),"#);
+
+    (METHOD_ENUM_DECODE) => (r#"
+            ({}, {}) => AmqpMethod::{}(try!(method::de(payload))),"#);
+
+    (METHOD_ENUM_ID) => (r#"
+            AmqpMethod::{}(_) => {},"#);
+
+    (METHOD_ENUM_NAME) => (r#"
+            AmqpMethod::{}(_) => "{}.{}","#);
 }
 
 macro_rules! fmt_src {
@@ -112,24 +225,28 @@ fn usage() {
 }
 
 fn gen_prot(json_path: String, rs_path: String) {
-    let mut json_file = File::open(json_path).unwrap();
-    let json = Json::from_reader(&mut json_file).unwrap();
+    let json_file = File::open(json_path).unwrap();
+    let json: Value = serde_json::from_reader(json_file).unwrap();
 
     let mut rs = String::new();
+    rs.push_str(USES);
     rs.push_str(&gen_header(&json));
     rs.push_str(&gen_consts(&json));
     rs.push('\n');
+    rs.push_str(&gen_method_ids(&json));
+    rs.push('\n');
     rs.push_str(&gen_classes(&json));
+    rs.push_str(&gen_method_enum(&json));
 
     let mut rs_file = File::create(rs_path).unwrap();
     rs_file.write_all(&rs.as_bytes()[1..]).unwrap();
 }
 
-fn gen_header(json: &Json) -> String {
+fn gen_header(json: &Value) -> String {
     let mut s = String::new();
     s.push_str(HEADER_STRUCT);
 
-    let name = json["name"].as_string().unwrap();
+    let name = json["name"].as_str().unwrap();
     let header = fmt_src!(HEADER, name,
                           json["major-version"], json["minor-version"], json["revision"]);
     s.push_str(&header);
@@ -146,9 +263,9 @@ fn gen_header(json: &Json) -> String {
     s
 }
 
-fn gen_consts(json: &Json) -> String {
+fn gen_consts(json: &Value) -> String {
     json["constants"].as_array().unwrap().iter().map(|c| {
-        let name = zstr::hyphen_to_snake(c["name"].as_string().unwrap());
+        let name = zstr::hyphen_to_snake(c["name"].as_str().unwrap());
         let value = c["value"].as_i64().unwrap();
         if value > u8::MAX as i64 {
             fmt_src!(CONST_U16, name, value as u16)
@@ -158,104 +275,239 @@ fn gen_consts(json: &Json) -> String {
     }).collect()
 }
 
-fn gen_classes(json: &Json) -> String {
+fn gen_method_ids(json: &Value) -> String {
+    let mut s = String::new();
+    for class in json["classes"].as_array().unwrap() {
+        let class_name = zstr::hyphen_to_snake(class["name"].as_str().unwrap());
+        for method in class["methods"].as_array().unwrap() {
+            let method_name = zstr::hyphen_to_snake(method["name"].as_str().unwrap());
+            let name = format!("{}_{}", class_name, method_name).to_uppercase();
+            s.push_str(&fmt_src!(CONST_METHOD_ID, name, class["id"], method["id"]));
+        }
+    }
+    s
+}
+
+fn gen_classes(json: &Value) -> String {
     let domains = get_domains(json);
     json["classes"].as_array().unwrap().iter().map(|class| {
         gen_class(class, &domains)
     }).collect()
 }
 
-fn get_domains(json: &Json) -> HashMap<&str, &str> {
+fn get_domains(json: &Value) -> HashMap<&str, &str> {
     json["domains"].as_array().unwrap().iter().map(|e| {
         let kv = e.as_array().unwrap();
-        (kv[0].as_string().unwrap(), kv[1].as_string().unwrap())
+        (kv[0].as_str().unwrap(), kv[1].as_str().unwrap())
     }).collect()
 }
 
-fn gen_class(class: &Json, domains: &HashMap<&str, &str>) -> String {
-    let class_name = class["name"].as_string().unwrap();
+fn gen_class(class: &Value, domains: &HashMap<&str, &str>) -> String {
+    let class_name = class["name"].as_str().unwrap();
     let mut methods = gen_methods(class, domains);
     let len = methods.len();
     methods.truncate(len - 1);
-    fmt_src!(CLASS_MOD, class_name, methods)
+
+    let has_refs = class["methods"].as_array().unwrap().iter().any(|method| {
+        has_str_args(method, domains)
+    });
+    let uses = if has_refs {
+        "Method, MethodRef, Reader, Writer"
+    } else {
+        "Method, Reader, Writer"
+    };
+    fmt_src!(CLASS_MOD, class_name, uses, methods)
 }
 
-fn gen_methods(class: &Json, domains: &HashMap<&str, &str>) -> String {
+fn gen_methods(class: &Value, domains: &HashMap<&str, &str>) -> String {
     class["methods"].as_array().unwrap().iter().map(|method| {
         gen_method(class, method, domains)
     }).collect()
 }
 
-fn gen_method(class: &Json, method: &Json, domains: &HashMap<&str, &str>) -> String {
+fn gen_method(class: &Value, method: &Value, domains: &HashMap<&str, &str>) -> String {
     let mut s = String::new();
     s.push_str(&gen_method_struct(method, domains));
-    s.push_str(&gen_method_impl(method));
+    s.push_str(&gen_method_impl(method, domains));
     s.push_str(&gen_method_default(class, method));
+    if has_str_args(method, domains) {
+        s.push_str(&gen_method_ref(method, domains));
+    }
     s
 }
 
-fn gen_method_struct(method: &Json, domains: &HashMap<&str, &str>) -> String {
-    let name = zstr::hyphen_to_camel(method["name"].as_string().unwrap());
+fn has_str_args(method: &Value, domains: &HashMap<&str, &str>) -> bool {
+    method["arguments"].as_array().unwrap().iter().any(|arg| {
+        match get_field_type(arg, domains) {
+            "shortstr" | "longstr" => true,
+            _ => false,
+        }
+    })
+}
+
+fn gen_method_struct(method: &Value, domains: &HashMap<&str, &str>) -> String {
+    let name = zstr::hyphen_to_camel(method["name"].as_str().unwrap());
     let fields = gen_method_struct_fields(method, domains);
     fmt_src!(METHOD_STRUCT, name, fields)
 }
 
-fn gen_method_struct_fields(method: &Json, domains: &HashMap<&str, &str>) -> String {
+fn gen_method_struct_fields(method: &Value, domains: &HashMap<&str, &str>) -> String {
     method["arguments"].as_array().unwrap().iter().map(|arg| {
         gen_method_struct_field(arg, domains)
     }).collect()
 }
 
-fn gen_method_struct_field(arg: &Json, domains: &HashMap<&str, &str>) -> String {
+fn gen_method_struct_field(arg: &Value, domains: &HashMap<&str, &str>) -> String {
     let name = get_field_name(arg);
-    let value = match arg.find("type") {
-        Some(v) => v.as_string().unwrap(),
-        None => domains[arg["domain"].as_string().unwrap()],
-    };
-    let ty = zstr::hyphen_to_camel(value);
+    let ty = zstr::hyphen_to_camel(get_field_type(arg, domains));
 
     fmt_src!(METHOD_STRUCT_FIELD, name, ty)
 }
 
-fn gen_method_impl(method: &Json) -> String {
-    let name = zstr::hyphen_to_camel(method["name"].as_string().unwrap());
-    let impl_str_type = gen_method_impl_str_type(method);
-    fmt_src!(METHOD_IMPL, name, impl_str_type)
+fn gen_method_impl(method: &Value, domains: &HashMap<&str, &str>) -> String {
+    let name = zstr::hyphen_to_camel(method["name"].as_str().unwrap());
+    let mut encode = String::new();
+    let mut decode = String::new();
+    let mut fields = String::new();
+
+    for arg in wire_args(method, domains) {
+        let (field, ty) = match arg {
+            WireArg::Bits(bits) => {
+                let values: Vec<String> = bits.iter().map(|bit| format!("self.{}", bit)).collect();
+                encode.push_str(&fmt_src!(ENCODE_BITS, values.join(", ")));
+                decode.push_str(&fmt_src!(DECODE_BITS, bits[0]));
+                for (pos, bit) in bits.iter().enumerate() {
+                    decode.push_str(&fmt_src!(DECODE_BIT, bit, 1 << pos));
+                    fields.push_str(&fmt_src!(METHOD_FIELD, bit, bit));
+                }
+                continue;
+            },
+            WireArg::Field(field, ty) => (field, ty),
+        };
+
+        let wire = match ty {
+            "timestamp" => "longlong",
+            "shortstr" => "short_str",
+            "longstr" => "long_str",
+            other => other,
+        };
+        match wire {
+            "short_str" | "table" => encode.push_str(&fmt_src!(ENCODE_FIELD_TRY, wire, field)),
+            "long_str" => encode.push_str(&fmt_src!(ENCODE_FIELD_REF, wire, field)),
+            _ => encode.push_str(&fmt_src!(ENCODE_FIELD, wire, field)),
+        }
+        decode.push_str(&fmt_src!(DECODE_FIELD, field, wire, field));
+        fields.push_str(&fmt_src!(METHOD_FIELD, field, field));
+    }
+
+    fmt_src!(METHOD_IMPL, name, encode, name, decode, name, fields)
 }
 
-fn gen_method_impl_str_type(method: &Json) -> String {
-    let mut patterns = String::new();
+// Same as the owned decoder, except that strings are borrowed from the payload.
+fn gen_method_ref(method: &Value, domains: &HashMap<&str, &str>) -> String {
+    let name = zstr::hyphen_to_camel(method["name"].as_str().unwrap());
+    let mut struct_fields = String::new();
+    let mut decode = String::new();
+    let mut fields = String::new();
+    let mut owned_fields = String::new();
+
+    for arg in wire_args(method, domains) {
+        let (field, ty) = match arg {
+            WireArg::Bits(bits) => {
+                decode.push_str(&fmt_src!(DECODE_BITS, bits[0]));
+                for (pos, bit) in bits.iter().enumerate() {
+                    struct_fields.push_str(&fmt_src!(METHOD_STRUCT_FIELD, bit, "Bit"));
+                    decode.push_str(&fmt_src!(DECODE_BIT, bit, 1 << pos));
+                    fields.push_str(&fmt_src!(METHOD_FIELD, bit, bit));
+                    owned_fields.push_str(&fmt_src!(METHOD_REF_OWNED_FIELD, bit, bit, ""));
+                }
+                continue;
+            },
+            WireArg::Field(field, ty) => (field, ty),
+        };
 
-    let args = method["arguments"].as_array().unwrap();
-    for arg in args {
-        let name = get_field_name(arg);
-        if let Some(ty) = arg.find("type") {
-            match ty.as_string().unwrap() {
-                "shortstr" => patterns.push_str(&fmt_src!(STR_TYPE_SHORT_STR, name)),
-                "longstr" => patterns.push_str(&fmt_src!(STR_TYPE_LONG_STR, name)),
-                _ => continue,
-            }
+        let (field_ty, wire, to_owned) = match ty {
+            "shortstr" => ("&'a str".to_string(), "short_str_ref", ".to_string()"),
+            "longstr" => ("&'a [u8]".to_string(), "long_str_ref", ".to_vec()"),
+            "timestamp" => (zstr::hyphen_to_camel(ty), "longlong", ""),
+            other => (zstr::hyphen_to_camel(other), other, ""),
+        };
+        struct_fields.push_str(&fmt_src!(METHOD_STRUCT_FIELD, field, field_ty));
+        decode.push_str(&fmt_src!(DECODE_FIELD, field, wire, field));
+        fields.push_str(&fmt_src!(METHOD_FIELD, field, field));
+        owned_fields.push_str(&fmt_src!(METHOD_REF_OWNED_FIELD, field, field, to_owned));
+    }
+
+    fmt_src!(METHOD_REF, name, struct_fields, name, name, name, decode, name, fields,
+             name, name, owned_fields)
+}
+
+// A method's arguments in wire order, with each run of bit arguments packed into one octet.
+enum WireArg<'a> {
+    Bits(Vec<String>),
+    Field(String, &'a str),
+}
+
+struct WireArgs<'a> {
+    args: &'a [Value],
+    domains: &'a HashMap<&'a str, &'a str>,
+    i: usize,
+}
+
+impl<'a> Iterator for WireArgs<'a> {
+    type Item = WireArg<'a>;
+
+    fn next(&mut self) -> Option<WireArg<'a>> {
+        let ty = match self.args.get(self.i) {
+            Some(arg) => get_field_type(arg, self.domains),
+            None => return None,
+        };
+        if ty != "bit" {
+            self.i += 1;
+            return Some(WireArg::Field(get_field_name(&self.args[self.i - 1]), ty));
+        }
+
+        let mut bits = Vec::new();
+        while self.i < self.args.len() &&
+              get_field_type(&self.args[self.i], self.domains) == "bit" {
+            bits.push(get_field_name(&self.args[self.i]));
+            self.i += 1;
         }
+        Some(WireArg::Bits(bits))
     }
-    fmt_src!(METHOD_IMPL_STR_TYPE, patterns)
 }
 
-fn gen_method_default(class: &Json, method: &Json) -> String {
-    let name = zstr::hyphen_to_camel(method["name"].as_string().unwrap());
+fn wire_args<'a>(method: &'a Value, domains: &'a HashMap<&'a str, &'a str>) -> WireArgs<'a> {
+    WireArgs {
+        args: method["arguments"].as_array().unwrap(),
+        domains: domains,
+        i: 0,
+    }
+}
+
+fn get_field_type<'a>(arg: &'a Value, domains: &HashMap<&str, &'a str>) -> &'a str {
+    match arg.get("type") {
+        Some(v) => v.as_str().unwrap(),
+        None => domains[arg["domain"].as_str().unwrap()],
+    }
+}
+
+fn gen_method_default(class: &Value, method: &Value) -> String {
+    let name = zstr::hyphen_to_camel(method["name"].as_str().unwrap());
     let cid = class["id"].as_u64().unwrap();
     let id = method["id"].as_u64().unwrap();
     let fields = gen_method_default_fields(method);
     fmt_src!(METHOD_DEFAULT, name, name, name, cid, id, fields)
 }
 
-fn gen_method_default_fields(method: &Json) -> String {
+fn gen_method_default_fields(method: &Value) -> String {
     method["arguments"].as_array().unwrap().iter().map(|arg| {
         let name = get_field_name(arg);
-        let value = match arg.find("default-value") {
+        let value = match arg.get("default-value") {
             Some(json) => {
-                let ty = match arg.find("type") {
-                    Some(ty_json) => ty_json.as_string().unwrap(),
-                    None => arg.find("domain").unwrap().as_string().unwrap(),
+                let ty = match arg.get("type") {
+                    Some(ty_json) => ty_json.as_str().unwrap(),
+                    None => arg.get("domain").unwrap().as_str().unwrap(),
                 };
                 get_field_value(json, ty)
             },
@@ -266,37 +518,64 @@ fn gen_method_default_fields(method: &Json) -> String {
     }).collect()
 }
 
-fn get_field_name(arg: &Json) -> String {
-    let mut name = zstr::hyphen_to_snake(arg["name"].as_string().unwrap());
+fn gen_method_enum(json: &Value) -> String {
+    let mut variants = String::new();
+    let mut decodes = String::new();
+    let mut class_ids = String::new();
+    let mut method_ids = String::new();
+    let mut names = String::new();
+
+    for class in json["classes"].as_array().unwrap() {
+        let class_name = class["name"].as_str().unwrap();
+        let cid = class["id"].as_u64().unwrap();
+        for method in class["methods"].as_array().unwrap() {
+            let method_name = method["name"].as_str().unwrap();
+            let id = method["id"].as_u64().unwrap();
+            let ty = zstr::hyphen_to_camel(method_name);
+            let variant = format!("{}{}", zstr::hyphen_to_camel(class_name), ty);
+
+            variants.push_str(&fmt_src!(METHOD_ENUM_VARIANT, variant, class_name, ty));
+            decodes.push_str(&fmt_src!(METHOD_ENUM_DECODE, cid, id, variant));
+            class_ids.push_str(&fmt_src!(METHOD_ENUM_ID, variant, cid));
+            method_ids.push_str(&fmt_src!(METHOD_ENUM_ID, variant, id));
+            names.push_str(&fmt_src!(METHOD_ENUM_NAME, variant, class_name, method_name));
+        }
+    }
+
+    fmt_src!(METHOD_ENUM, variants, decodes, class_ids, method_ids, names)
+}
+
+fn get_field_name(arg: &Value) -> String {
+    let mut name = zstr::hyphen_to_snake(arg["name"].as_str().unwrap());
     if name == "type" {
         name = "ty".to_string();
     }
     name
 }
 
-fn get_field_value(json: &Json, ty: &str) -> String {
+fn get_field_value(json: &Value, ty: &str) -> String {
     match json {
-        &Json::String(_) => {
+        &Value::String(_) => {
             match ty {
                 "shortstr" => get_short_str_value(json),
                 "longstr" => get_long_str_value(json),
                 _ => panic!("{}", ty),
             }
         },
-        &Json::Object(_) => get_object_value(json),
+        &Value::Object(_) => get_object_value(json),
         _ => get_other_value(json),
     }
 }
 
-fn get_short_str_value(json: &Json) -> String {
+fn get_short_str_value(json: &Value) -> String {
     format!("{}.to_string()", json)
 }
 
-fn get_long_str_value(json: &Json) -> String {
+fn get_long_str_value(json: &Value) -> String {
     format!("b{}.to_vec()", json)
 }
 
-fn get_object_value(json: &Json) -> String {
+fn get_object_value(json: &Value) -> String {
     if json.as_object().unwrap().is_empty() {
         DEFAULT_CALL.to_string()
     } else {
@@ -304,7 +583,7 @@ fn get_object_value(json: &Json) -> String {
     }
 }
 
-fn get_other_value(json: &Json) -> String {
+fn get_other_value(json: &Value) -> String {
     format!("{}", json)
 }
 
@@ -0,0 +1,1216 @@
+use bytes::Bytes;
+
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::io::{self, Cursor, Read};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use compression::{self, Compressor};
+use consumer::{Consumer, ConsumerEvent, Delivery, ReturnedMessage};
+use content::{BasicProperties, ContentHeader};
+use frame::{self, Frame};
+use method::{Method, MethodRef};
+use protocol::{self, basic, channel, confirm, exchange, queue, tx};
+use protocol::{BASIC_ACK, BASIC_CANCEL, BASIC_DELIVER, BASIC_NACK, BASIC_RETURN, CHANNEL_CLOSE,
+               CHANNEL_FLOW};
+use result::*;
+use transport::Transport;
+use types::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloseReason {
+    pub reply_code: u16,
+    pub reply_text: String,
+    pub class_id: u16,
+    pub method_id: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Normal,
+    Confirm,
+    Tx,
+}
+
+pub struct Channel {
+    id: u16,
+    tx: Rc<RefCell<Transport>>,
+    frame_max: u32,
+    mode: Mode,
+    active: bool,
+    close_reason: Option<CloseReason>,
+    pending: VecDeque<Frame>,
+    consumers: HashMap<Shortstr, Consumer>,
+    events: VecDeque<ConsumerEvent>,
+    unacked: BTreeSet<Longlong>,
+    next_publish_seq: Longlong,
+    unconfirmed: BTreeSet<Longlong>,
+    nacked: bool,
+    returns: VecDeque<ReturnedMessage>,
+    compressor: Option<Compressor>,
+}
+
+impl Channel {
+    pub fn new(id: u16, tx: Rc<RefCell<Transport>>, frame_max: u32) -> Channel {
+        Channel {
+            id: id,
+            tx: tx,
+            frame_max: frame_max,
+            mode: Mode::Normal,
+            active: true,
+            close_reason: None,
+            pending: VecDeque::new(),
+            consumers: HashMap::new(),
+            events: VecDeque::new(),
+            unacked: BTreeSet::new(),
+            next_publish_seq: 0,
+            unconfirmed: BTreeSet::new(),
+            nacked: false,
+            returns: VecDeque::new(),
+            compressor: None,
+        }
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn compressor(&self) -> Option<Compressor> {
+        self.compressor
+    }
+
+    pub fn set_compressor(&mut self, compressor: Option<Compressor>) {
+        self.compressor = compressor;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.close_reason.is_none()
+    }
+
+    pub fn close_reason(&self) -> Option<&CloseReason> {
+        self.close_reason.as_ref()
+    }
+
+    pub fn open(&mut self) -> AmqpResult<()> {
+        let open = channel::Open::default();
+        let _: channel::OpenOk = try!(self.rpc(&open));
+        Ok(())
+    }
+
+    pub fn close(&mut self) -> AmqpResult<()> {
+        if !self.is_open() {
+            return Ok(());
+        }
+
+        let mut close = channel::Close::default();
+        close.reply_code = protocol::REPLY_SUCCESS as u16;
+        close.reply_text = "OK".to_string();
+        let _: channel::CloseOk = try!(self.rpc(&close));
+
+        self.close_reason = Some(CloseReason {
+            reply_code: close.reply_code,
+            reply_text: close.reply_text,
+            class_id: 0,
+            method_id: 0,
+        });
+        Ok(())
+    }
+
+    pub fn flow(&mut self, active: bool) -> AmqpResult<bool> {
+        let mut flow = channel::Flow::default();
+        flow.active = active;
+        let flow_ok: channel::FlowOk = try!(self.rpc(&flow));
+        Ok(flow_ok.active)
+    }
+
+    pub fn confirm_select(&mut self) -> AmqpResult<()> {
+        match self.mode {
+            Mode::Confirm => return Ok(()),
+            Mode::Tx => return Err(AmqpError::ChannelModeErr(self.mode, Mode::Confirm)),
+            Mode::Normal => {},
+        }
+
+        let select = confirm::Select::default();
+        let _: confirm::SelectOk = try!(self.rpc(&select));
+        self.mode = Mode::Confirm;
+        self.next_publish_seq = 1;
+        Ok(())
+    }
+
+    // The sequence number the broker will confirm the next publish under, 0 outside confirm mode.
+    pub fn next_publish_seq(&self) -> Longlong {
+        self.next_publish_seq
+    }
+
+    pub fn unconfirmed(&self) -> &BTreeSet<Longlong> {
+        &self.unconfirmed
+    }
+
+    // Waits until every publish so far is acked or nacked; false if any was nacked since the
+    // last wait.
+    pub fn wait_for_confirms(&mut self) -> AmqpResult<bool> {
+        try!(self.expect_mode(Mode::Confirm));
+        while !self.unconfirmed.is_empty() {
+            try!(self.pump());
+        }
+        Ok(!mem::replace(&mut self.nacked, false))
+    }
+
+    // Mandatory or immediate messages the broker could not route, in the order they came back.
+    pub fn next_return(&mut self) -> Option<ReturnedMessage> {
+        self.returns.pop_front()
+    }
+
+    pub fn tx_select(&mut self) -> AmqpResult<()> {
+        match self.mode {
+            Mode::Tx => return Ok(()),
+            Mode::Confirm => return Err(AmqpError::ChannelModeErr(self.mode, Mode::Tx)),
+            Mode::Normal => {},
+        }
+
+        let select = tx::Select::default();
+        let _: tx::SelectOk = try!(self.rpc(&select));
+        self.mode = Mode::Tx;
+        Ok(())
+    }
+
+    pub fn tx_commit(&mut self) -> AmqpResult<()> {
+        try!(self.expect_mode(Mode::Tx));
+        let commit = tx::Commit::default();
+        let _: tx::CommitOk = try!(self.rpc(&commit));
+        Ok(())
+    }
+
+    pub fn tx_rollback(&mut self) -> AmqpResult<()> {
+        try!(self.expect_mode(Mode::Tx));
+        let rollback = tx::Rollback::default();
+        let _: tx::RollbackOk = try!(self.rpc(&rollback));
+        Ok(())
+    }
+
+    pub fn transaction(&mut self) -> AmqpResult<Transaction> {
+        try!(self.tx_select());
+        Ok(Transaction {
+            channel: self,
+            done: false,
+        })
+    }
+
+    pub fn queue_declare(&mut self, declare: &queue::Declare) -> AmqpResult<queue::DeclareOk> {
+        if declare.nowait {
+            try!(self.send(declare));
+            let mut declare_ok = queue::DeclareOk::default();
+            declare_ok.queue = declare.queue.clone();
+            return Ok(declare_ok);
+        }
+        self.rpc(declare)
+    }
+
+    pub fn queue_bind(&mut self, bind: &queue::Bind) -> AmqpResult<()> {
+        if bind.nowait {
+            return self.send(bind);
+        }
+        let _: queue::BindOk = try!(self.rpc(bind));
+        Ok(())
+    }
+
+    pub fn exchange_declare(&mut self, declare: &exchange::Declare) -> AmqpResult<()> {
+        if declare.nowait {
+            return self.send(declare);
+        }
+        let _: exchange::DeclareOk = try!(self.rpc(declare));
+        Ok(())
+    }
+
+    pub fn basic_publish(&mut self, exchange: &str, routing_key: &str, props: &BasicProperties,
+                         body: &[u8]) -> AmqpResult<()> {
+        let mut props = props.clone();
+        let compressed = match self.compressor {
+            Some(ref compressor) => try!(compressor.compress(&mut props, body)),
+            None => None,
+        };
+        let body = compressed.as_ref().map_or(body, |v| &v[..]);
+
+        let (id, frame_max) = (self.id, self.body_frame_max());
+        self.write_message(exchange, routing_key, props, body.len() as u64, |tx| {
+            for chunk in body.chunks(frame_max) {
+                try!(tx.write_frame(protocol::FRAME_BODY, id, chunk));
+            }
+            Ok(())
+        })
+    }
+
+    // The body size goes out in the content header before the first byte is read, so the
+    // body is neither compressed nor buffered; a reader that fails or ends early once part of
+    // the message has been written out leaves the channel closed, see write_message.
+    pub fn basic_publish_from<R: Read>(&mut self, exchange: &str, routing_key: &str,
+                                       props: &BasicProperties, body: &mut R, body_size: u64)
+                                       -> AmqpResult<()> {
+        let (id, frame_max) = (self.id, self.body_frame_max() as u64);
+        self.write_message(exchange, routing_key, props.clone(), body_size, |tx| {
+            let mut buf = vec![0u8; cmp::min(frame_max, body_size) as usize];
+            let mut remaining = body_size;
+            while remaining > 0 {
+                let chunk = &mut buf[..cmp::min(frame_max, remaining) as usize];
+                try!(body.read_exact(chunk));
+                try!(tx.write_frame(protocol::FRAME_BODY, id, chunk));
+                remaining -= chunk.len() as u64;
+            }
+            Ok(())
+        })
+    }
+
+    pub fn basic_ack(&mut self, delivery_tag: u64, multiple: bool) -> AmqpResult<()> {
+        let mut ack = basic::Ack::default();
+        ack.delivery_tag = delivery_tag;
+        ack.multiple = multiple;
+        try!(self.send(&ack));
+        self.settle(delivery_tag, multiple);
+        Ok(())
+    }
+
+    pub fn basic_nack(&mut self, delivery_tag: u64, multiple: bool, requeue: bool)
+                      -> AmqpResult<()> {
+        let mut nack = basic::Nack::default();
+        nack.delivery_tag = delivery_tag;
+        nack.multiple = multiple;
+        nack.requeue = requeue;
+        try!(self.send(&nack));
+        self.settle(delivery_tag, multiple);
+        Ok(())
+    }
+
+    pub fn basic_reject(&mut self, delivery_tag: u64, requeue: bool) -> AmqpResult<()> {
+        let mut reject = basic::Reject::default();
+        reject.delivery_tag = delivery_tag;
+        reject.requeue = requeue;
+        try!(self.send(&reject));
+        self.settle(delivery_tag, false);
+        Ok(())
+    }
+
+    pub fn basic_recover(&mut self, requeue: bool) -> AmqpResult<()> {
+        let mut recover = basic::Recover::default();
+        recover.requeue = requeue;
+        let _: basic::RecoverOk = try!(self.rpc(&recover));
+        self.recovered();
+        Ok(())
+    }
+
+    pub fn basic_recover_async(&mut self, requeue: bool) -> AmqpResult<()> {
+        let mut recover_async = basic::RecoverAsync::default();
+        recover_async.requeue = requeue;
+        try!(self.send(&recover_async));
+        self.recovered();
+        Ok(())
+    }
+
+    pub fn unacked(&self) -> &BTreeSet<Longlong> {
+        &self.unacked
+    }
+
+    pub fn basic_qos(&mut self, prefetch_size: u32, prefetch_count: u16, global: bool)
+                     -> AmqpResult<()> {
+        let mut qos = basic::Qos::default();
+        qos.prefetch_size = prefetch_size;
+        qos.prefetch_count = prefetch_count;
+        qos.global = global;
+        let _: basic::QosOk = try!(self.rpc(&qos));
+        Ok(())
+    }
+
+    pub fn basic_consume(&mut self, consumer: &Consumer) -> AmqpResult<Shortstr> {
+        let mut consume = basic::Consume::default();
+        consume.queue = consumer.queue.clone();
+        consume.consumer_tag = consumer.consumer_tag.clone();
+        consume.no_local = consumer.no_local;
+        consume.no_ack = consumer.no_ack;
+        consume.exclusive = consumer.exclusive;
+        consume.arguments = consumer.arguments.clone();
+        let consume_ok: basic::ConsumeOk = try!(self.rpc(&consume));
+
+        self.consumers.insert(consume_ok.consumer_tag.clone(), consumer.clone());
+        Ok(consume_ok.consumer_tag)
+    }
+
+    pub fn basic_cancel(&mut self, consumer_tag: &str) -> AmqpResult<()> {
+        let mut cancel = basic::Cancel::default();
+        cancel.consumer_tag = consumer_tag.to_string();
+        let _: basic::CancelOk = try!(self.rpc(&cancel));
+
+        self.consumers.remove(consumer_tag);
+        Ok(())
+    }
+
+    pub fn consumers(&self) -> &HashMap<Shortstr, Consumer> {
+        &self.consumers
+    }
+
+    pub fn next_event(&mut self) -> AmqpResult<ConsumerEvent> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+            try!(self.pump());
+        }
+    }
+
+    pub fn next_event_timeout(&mut self, timeout: Duration) -> AmqpResult<ConsumerEvent> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(AmqpError::Timeout);
+            }
+
+            self.tx.borrow_mut().set_timeout(Some(deadline - now));
+            let result = self.pump();
+            self.tx.borrow_mut().set_timeout(None);
+            try!(result);
+        }
+    }
+
+    pub fn next_delivery_reader(&mut self) -> AmqpResult<DeliveryReader> {
+        loop {
+            match self.events.pop_front() {
+                Some(ConsumerEvent::Delivery(delivery)) => {
+                    return Ok(DeliveryReader::buffered(self, delivery));
+                },
+                Some(ConsumerEvent::Cancelled(consumer_tag, _)) => {
+                    return Err(AmqpError::ConsumerCancelled(consumer_tag));
+                },
+                None => (),
+            }
+
+            try!(self.check_open());
+            let frame = try!(self.tx.borrow_mut().recv_on(self.id));
+            if frame.ty == protocol::FRAME_METHOD && try!(frame.method_id()) == BASIC_DELIVER {
+                let deliver = try!(basic::DeliverRef::decode(&frame.payload));
+                let header = try!(self.recv_header());
+                self.track_delivery(deliver.consumer_tag, deliver.delivery_tag);
+                return Ok(DeliveryReader::streaming(self, deliver, header));
+            }
+            if !try!(self.handle_async(&frame)) {
+                self.pending.push_back(frame);
+            }
+        }
+    }
+
+    fn expect_mode(&self, mode: Mode) -> AmqpResult<()> {
+        if self.mode != mode {
+            Err(AmqpError::ChannelModeErr(self.mode, mode))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_open(&self) -> AmqpResult<()> {
+        match self.close_reason {
+            Some(ref reason) => Err(AmqpError::ChannelClosed {
+                reply_code: reason.reply_code,
+                reply_text: reason.reply_text.clone(),
+                class_id: reason.class_id,
+                method_id: reason.method_id,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn settle(&mut self, delivery_tag: Longlong, multiple: bool) {
+        if multiple && delivery_tag == 0 {
+            self.unacked.clear();
+        } else {
+            remove_tags(&mut self.unacked, delivery_tag, multiple);
+        }
+    }
+
+    // The broker redelivers every unacked message, so copies still queued here would be
+    // seen twice.
+    fn recovered(&mut self) {
+        let unacked = mem::replace(&mut self.unacked, BTreeSet::new());
+        self.events.retain(|event| match *event {
+            ConsumerEvent::Delivery(ref delivery) => !unacked.contains(&delivery.delivery_tag),
+            _ => true,
+        });
+    }
+
+    fn track_delivery(&mut self, consumer_tag: &str, delivery_tag: u64) {
+        let no_ack = self.consumers.get(consumer_tag).map_or(false, |c| c.no_ack);
+        if !no_ack {
+            self.unacked.insert(delivery_tag);
+        }
+    }
+
+    fn body_frame_max(&self) -> usize {
+        frame::body_frame_max(self.frame_max)
+    }
+
+    fn wait_active(&mut self) -> AmqpResult<()> {
+        while !self.active {
+            try!(self.pump());
+        }
+        Ok(())
+    }
+
+    fn pump(&mut self) -> AmqpResult<()> {
+        try!(self.check_open());
+        let frame = try!(self.tx.borrow_mut().recv_on(self.id));
+        if !try!(self.handle_async(&frame)) {
+            self.pending.push_back(frame);
+        }
+        Ok(())
+    }
+
+    fn handle_async(&mut self, frame: &Frame) -> AmqpResult<bool> {
+        if frame.ty != protocol::FRAME_METHOD {
+            return Ok(false);
+        }
+
+        match try!(frame.method_id()) {
+            CHANNEL_FLOW => {
+                let flow: channel::Flow = try!(frame.to_method());
+                self.active = flow.active;
+
+                let mut flow_ok = channel::FlowOk::default();
+                flow_ok.active = flow.active;
+                try!(self.send(&flow_ok));
+                Ok(true)
+            },
+            CHANNEL_CLOSE => {
+                let close: channel::Close = try!(frame.to_method());
+                try!(self.send(&channel::CloseOk::default()));
+
+                self.close_reason = Some(CloseReason {
+                    reply_code: close.reply_code,
+                    reply_text: close.reply_text,
+                    class_id: close.class_id,
+                    method_id: close.method_id,
+                });
+                try!(self.check_open());
+                Ok(true)
+            },
+            BASIC_CANCEL => {
+                let cancel: basic::Cancel = try!(frame.to_method());
+                if let Some(consumer) = self.consumers.remove(&cancel.consumer_tag) {
+                    self.events.push_back(ConsumerEvent::Cancelled(cancel.consumer_tag.clone(),
+                                                                   consumer));
+                }
+
+                if !cancel.nowait {
+                    let mut cancel_ok = basic::CancelOk::default();
+                    cancel_ok.consumer_tag = cancel.consumer_tag;
+                    try!(self.send(&cancel_ok));
+                }
+                Ok(true)
+            },
+            BASIC_ACK | BASIC_NACK => {
+                let (delivery_tag, multiple) = if try!(frame.method_id()) == BASIC_ACK {
+                    let ack: basic::Ack = try!(frame.to_method());
+                    (ack.delivery_tag, ack.multiple)
+                } else {
+                    let nack: basic::Nack = try!(frame.to_method());
+                    self.nacked = true;
+                    (nack.delivery_tag, nack.multiple)
+                };
+                remove_tags(&mut self.unconfirmed, delivery_tag, multiple);
+                Ok(true)
+            },
+            BASIC_RETURN => {
+                let ret: basic::Return = try!(frame.to_method());
+                let (properties, body) = try!(self.recv_content());
+                self.returns.push_back(ReturnedMessage {
+                    reply_code: ret.reply_code,
+                    reply_text: ret.reply_text,
+                    exchange: ret.exchange,
+                    routing_key: ret.routing_key,
+                    properties: properties,
+                    body: body,
+                });
+                Ok(true)
+            },
+            // The strings of a delivery are copied once, straight out of the frame.
+            BASIC_DELIVER => {
+                let deliver = try!(basic::DeliverRef::decode(&frame.payload));
+                let (properties, body) = try!(self.recv_content());
+                self.track_delivery(deliver.consumer_tag, deliver.delivery_tag);
+
+                let mut delivery = Delivery {
+                    consumer_tag: deliver.consumer_tag.to_string(),
+                    delivery_tag: deliver.delivery_tag,
+                    redelivered: deliver.redelivered,
+                    exchange: deliver.exchange.to_string(),
+                    routing_key: deliver.routing_key.to_string(),
+                    properties: properties,
+                    body: body,
+                };
+                // A body that fails to decompress is delivered untouched, see Delivery.
+                let _ = match self.compressor {
+                    Some(ref compressor) => compressor.decompress(&mut delivery),
+                    None => delivery.decompress(compression::DEFAULT_MAX_SIZE),
+                };
+                self.events.push_back(ConsumerEvent::Delivery(delivery));
+                Ok(true)
+            },
+            _ => Ok(false),
+        }
+    }
+
+    // Buffers the publish method and content header, lets write_body add the body frames,
+    // and sends them all in one write unless the body spills early. If any step fails before
+    // a byte of the message went out, it is dropped as if never published. Otherwise the
+    // broker holds a partial message it waits on the rest of, which nothing can send without
+    // breaking the connection, so the channel is closed.
+    fn write_message<F>(&mut self, exchange: &str, routing_key: &str, props: BasicProperties,
+                        body_size: u64, write_body: F) -> AmqpResult<()>
+        where F: FnOnce(&mut Transport) -> AmqpResult<()>
+    {
+        try!(self.wait_active());
+        try!(self.check_open());
+
+        let mut publish = basic::Publish::default();
+        publish.exchange = exchange.to_string();
+        publish.routing_key = routing_key.to_string();
+        let header = try!(ContentHeader::new(body_size, props).se());
+
+        let id = self.id;
+        let mut tx = self.tx.borrow_mut();
+        let mark = tx.mark();
+        let result = tx.write_method(id, &publish)
+                       .and_then(|_| tx.write_frame(protocol::FRAME_HEADER, id, &header))
+                       .and_then(|_| write_body(&mut tx))
+                       .and_then(|_| tx.flush());
+        if let Err(err) = result {
+            if !tx.rollback(mark) {
+                self.close_reason = Some(CloseReason {
+                    reply_code: protocol::INTERNAL_ERROR,
+                    reply_text: "message partly sent".to_string(),
+                    class_id: protocol::BASIC_PUBLISH.0,
+                    method_id: protocol::BASIC_PUBLISH.1,
+                });
+            }
+            return Err(err);
+        }
+
+        if self.mode == Mode::Confirm {
+            self.unconfirmed.insert(self.next_publish_seq);
+            self.next_publish_seq += 1;
+        }
+        Ok(())
+    }
+
+    // Once the method frame of a message is read its content has to follow, so an event
+    // timeout no longer applies; giving up half way would lose the message and leave its
+    // frames ahead of the next reply.
+    fn recv_header(&mut self) -> AmqpResult<ContentHeader> {
+        self.tx.borrow_mut().set_timeout(None);
+        let frame = try!(self.tx.borrow_mut().recv_on(self.id));
+        try!(expect_frame_type(&frame, protocol::FRAME_HEADER));
+        frame.to_content_header()
+    }
+
+    fn recv_content(&mut self) -> AmqpResult<(BasicProperties, Bytes)> {
+        let header = try!(self.recv_header());
+
+        let mut parts = Vec::new();
+        let mut size = 0;
+        while size < header.body_size {
+            let frame = try!(self.tx.borrow_mut().recv_on(self.id));
+            try!(expect_frame_type(&frame, protocol::FRAME_BODY));
+            size += frame.payload.len() as u64;
+            parts.push(frame.payload);
+        }
+        Ok((header.properties, frame::join_body(parts)))
+    }
+
+    fn send<M: Method>(&self, method: &M) -> AmqpResult<()> {
+        try!(self.check_open());
+        let mut tx = self.tx.borrow_mut();
+        try!(tx.write_method(self.id, method));
+        tx.flush()
+    }
+
+    fn recv_frame(&mut self) -> AmqpResult<Frame> {
+        try!(self.check_open());
+        if let Some(frame) = self.pending.pop_front() {
+            return Ok(frame);
+        }
+
+        loop {
+            let frame = try!(self.tx.borrow_mut().recv_on(self.id));
+            if !try!(self.handle_async(&frame)) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    fn recv<M: Method>(&mut self) -> AmqpResult<M> {
+        let frame = try!(self.recv_frame());
+        frame.to_method()
+    }
+
+    fn rpc<M: Method, R: Method>(&mut self, method: &M) -> AmqpResult<R> {
+        try!(self.send(method));
+        self.recv()
+    }
+}
+
+// Removes the tag, or with multiple every tag up to and including it.
+fn remove_tags(tags: &mut BTreeSet<Longlong>, tag: Longlong, multiple: bool) {
+    if !multiple {
+        tags.remove(&tag);
+    } else {
+        match tag.checked_add(1) {
+            Some(next) => *tags = tags.split_off(&next),
+            None => tags.clear(),
+        }
+    }
+}
+
+fn expect_frame_type(frame: &Frame, ty: u8) -> AmqpResult<()> {
+    if frame.ty != ty {
+        Err(AmqpError::FrameTypeErr(frame.ty, ty))
+    } else {
+        Ok(())
+    }
+}
+
+pub struct Transaction<'a> {
+    channel: &'a mut Channel,
+    done: bool,
+}
+
+impl<'a> Transaction<'a> {
+    pub fn commit(mut self) -> AmqpResult<()> {
+        self.done = true;
+        self.channel.tx_commit()
+    }
+
+    pub fn rollback(mut self) -> AmqpResult<()> {
+        self.done = true;
+        self.channel.tx_rollback()
+    }
+}
+
+impl<'a> Deref for Transaction<'a> {
+    type Target = Channel;
+
+    fn deref(&self) -> &Channel {
+        self.channel
+    }
+}
+
+impl<'a> DerefMut for Transaction<'a> {
+    fn deref_mut(&mut self) -> &mut Channel {
+        self.channel
+    }
+}
+
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.channel.tx_rollback();
+        }
+    }
+}
+
+// A body streamed off the socket is not decompressed: it is read as sent, with body_size its
+// size on the wire and properties.content_encoding still set. Only a delivery that had
+// already been buffered went through the channel's compressor.
+pub struct DeliveryReader<'a> {
+    channel: &'a mut Channel,
+    pub consumer_tag: Shortstr,
+    pub delivery_tag: Longlong,
+    pub redelivered: bool,
+    pub exchange: Shortstr,
+    pub routing_key: Shortstr,
+    pub properties: BasicProperties,
+    pub body_size: Longlong,
+    buffered: Option<Cursor<Bytes>>,
+    remaining: Longlong,
+}
+
+impl<'a> DeliveryReader<'a> {
+    fn buffered(channel: &'a mut Channel, delivery: Delivery) -> DeliveryReader<'a> {
+        DeliveryReader {
+            channel: channel,
+            consumer_tag: delivery.consumer_tag,
+            delivery_tag: delivery.delivery_tag,
+            redelivered: delivery.redelivered,
+            exchange: delivery.exchange,
+            routing_key: delivery.routing_key,
+            properties: delivery.properties,
+            body_size: delivery.body.len() as Longlong,
+            buffered: Some(Cursor::new(delivery.body)),
+            remaining: 0,
+        }
+    }
+
+    fn streaming(channel: &'a mut Channel, deliver: basic::DeliverRef, header: ContentHeader)
+                 -> DeliveryReader<'a> {
+        DeliveryReader {
+            channel: channel,
+            consumer_tag: deliver.consumer_tag.to_string(),
+            delivery_tag: deliver.delivery_tag,
+            redelivered: deliver.redelivered,
+            exchange: deliver.exchange.to_string(),
+            routing_key: deliver.routing_key.to_string(),
+            properties: header.properties,
+            body_size: header.body_size,
+            buffered: None,
+            remaining: header.body_size,
+        }
+    }
+
+    pub fn channel(&mut self) -> &mut Channel {
+        self.channel
+    }
+}
+
+impl<'a> Read for DeliveryReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(ref mut body) = self.buffered {
+            return body.read(buf);
+        }
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+
+        let len = cmp::min(buf.len() as u64, self.remaining) as usize;
+        let result = self.channel.tx.borrow_mut().read_body(self.channel.id, &mut buf[..len]);
+        match result {
+            Ok(n) => {
+                self.remaining -= n as u64;
+                Ok(n)
+            },
+            Err(AmqpError::IoErr(err)) => Err(err),
+            Err(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        }
+    }
+}
+
+// The rest of an unread body is still on the wire, ahead of the channel's next frame.
+impl<'a> Drop for DeliveryReader<'a> {
+    fn drop(&mut self) {
+        if self.remaining > 0 {
+            let _ = io::copy(self, &mut io::sink());
+        }
+    }
+}
+
+#[cfg(test)]
+impl Channel {
+    // Open channel 1 on a connection whose broker end is the returned socket, with the
+    // handshake already read off it.
+    pub fn for_test(frame_max: u32) -> (Channel, ::socket::MemorySocket) {
+        use conf::Conf;
+        use protocol::connection;
+        use socket::Peer;
+
+        let (client, mut server) = ::socket::MemorySocket::pair();
+        let mut tune = connection::Tune::default();
+        tune.frame_max = frame_max;
+        server.write_method(0, &connection::Start::default());
+        server.write_method(0, &tune);
+        server.write_method(0, &connection::OpenOk::default());
+        server.write_method(1, &channel::OpenOk::default());
+
+        let mut tx = Transport::with_socket(&Conf::default(), Box::new(client));
+        tx.open().unwrap();
+        let mut channel = Channel::new(1, Rc::new(RefCell::new(tx)), frame_max);
+        channel.open().unwrap();
+
+        let mut header = [0u8; 8];
+        server.read_exact(&mut header).unwrap();
+        assert_eq!(header, protocol::PROT_HEADER);
+        server.read_frames();
+        (channel, server)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use socket::Peer;
+    use std::thread;
+
+    fn method_ids(frames: &[Frame]) -> Vec<(u16, u16)> {
+        frames.iter().filter(|f| f.ty == protocol::FRAME_METHOD)
+              .map(|f| f.method_id().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_tx() {
+        let (mut channel, mut server) = Channel::for_test(4096);
+        server.write_method(1, &tx::SelectOk::default());
+        server.write_method(1, &tx::CommitOk::default());
+        server.write_method(1, &tx::RollbackOk::default());
+        channel.tx_select().unwrap();
+        channel.tx_commit().unwrap();
+        channel.tx_rollback().unwrap();
+        assert_eq!(channel.mode(), Mode::Tx);
+        assert_eq!(method_ids(&server.read_frames()), vec![(90, 10), (90, 20), (90, 30)]);
+
+        match channel.confirm_select() {
+            Err(AmqpError::ChannelModeErr(Mode::Tx, Mode::Confirm)) => (),
+            other => panic!("{:?}", other),
+        }
+        assert!(server.read_frames().is_empty());
+    }
+
+    #[test]
+    fn test_transaction() {
+        let (mut channel, mut server) = Channel::for_test(4096);
+        server.write_method(1, &tx::SelectOk::default());
+        server.write_method(1, &tx::CommitOk::default());
+        server.write_method(1, &tx::RollbackOk::default());
+        {
+            let mut transaction = channel.transaction().unwrap();
+            transaction.basic_publish("", "jobs", &BasicProperties::default(), b"a").unwrap();
+            transaction.commit().unwrap();
+        }
+        {
+            let mut transaction = channel.transaction().unwrap();
+            transaction.basic_publish("", "jobs", &BasicProperties::default(), b"b").unwrap();
+        }
+        let frames = server.read_frames();
+        assert_eq!(method_ids(&frames), vec![(90, 10), (60, 40), (90, 20), (60, 40), (90, 30)]);
+
+        let (mut channel, _server) = Channel::for_test(4096);
+        match channel.tx_commit() {
+            Err(AmqpError::ChannelModeErr(Mode::Normal, Mode::Tx)) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_confirms() {
+        let (mut channel, mut server) = Channel::for_test(4096);
+        server.write_method(1, &confirm::SelectOk::default());
+        channel.confirm_select().unwrap();
+        match channel.tx_select() {
+            Err(AmqpError::ChannelModeErr(Mode::Confirm, Mode::Tx)) => (),
+            other => panic!("{:?}", other),
+        }
+
+        for _ in 0..3 {
+            channel.basic_publish("", "jobs", &BasicProperties::default(), b"job").unwrap();
+        }
+        assert_eq!(channel.next_publish_seq(), 4);
+        assert_eq!(channel.unconfirmed().len(), 3);
+
+        let mut ret = basic::Return::default();
+        ret.reply_code = protocol::NO_ROUTE;
+        ret.routing_key = "jobs".to_string();
+        server.write_method(1, &ret);
+        server.write_content(1, &BasicProperties::default(), b"job");
+        let mut ack = basic::Ack::default();
+        ack.delivery_tag = 2;
+        ack.multiple = true;
+        server.write_method(1, &ack);
+        let mut nack = basic::Nack::default();
+        nack.delivery_tag = 3;
+        server.write_method(1, &nack);
+        assert!(!channel.wait_for_confirms().unwrap());
+
+        let returned = channel.next_return().unwrap();
+        assert_eq!(returned.reply_code, protocol::NO_ROUTE);
+        assert_eq!(returned.routing_key, "jobs");
+        assert_eq!(returned.body, &b"job"[..]);
+        assert!(channel.next_return().is_none());
+
+        channel.basic_publish("", "jobs", &BasicProperties::default(), b"job").unwrap();
+        ack.delivery_tag = 4;
+        ack.multiple = false;
+        server.write_method(1, &ack);
+        assert!(channel.wait_for_confirms().unwrap());
+
+        server.write_method(1, &queue::DeclareOk::default());
+        channel.queue_declare(&queue::Declare::default()).unwrap();
+    }
+
+    fn consume(channel: &mut Channel, server: &mut ::socket::MemorySocket, no_ack: bool) {
+        let mut consume_ok = basic::ConsumeOk::default();
+        consume_ok.consumer_tag = "ctag".to_string();
+        server.write_method(1, &consume_ok);
+        let mut consumer = Consumer::new("jobs");
+        consumer.no_ack = no_ack;
+        assert_eq!(channel.basic_consume(&consumer).unwrap(), "ctag");
+        server.read_frames();
+    }
+
+    fn deliver(server: &mut ::socket::MemorySocket, delivery_tag: u64, body: &[u8]) {
+        let mut deliver = basic::Deliver::default();
+        deliver.consumer_tag = "ctag".to_string();
+        deliver.delivery_tag = delivery_tag;
+        deliver.routing_key = "jobs".to_string();
+        server.write_method(1, &deliver);
+        server.write_content(1, &BasicProperties::default(), body);
+    }
+
+    #[test]
+    fn test_deliver() {
+        let (mut channel, mut server) = Channel::for_test(4096);
+        consume(&mut channel, &mut server, false);
+
+        let mut deliver = basic::Deliver::default();
+        deliver.consumer_tag = "ctag".to_string();
+        deliver.delivery_tag = 1;
+        server.write_method(1, &deliver);
+        let header = ContentHeader::new(11, BasicProperties::default());
+        server.write_frame(protocol::FRAME_HEADER, 1, &header.se().unwrap());
+        server.write_frame(protocol::FRAME_BODY, 1, b"hello");
+        server.write_frame(protocol::FRAME_BODY, 1, b" world");
+
+        match channel.next_event().unwrap() {
+            ConsumerEvent::Delivery(delivery) => {
+                assert_eq!(delivery.consumer_tag, "ctag");
+                assert_eq!(delivery.delivery_tag, 1);
+                assert_eq!(delivery.body, &b"hello world"[..]);
+            },
+            other => panic!("{:?}", other),
+        }
+        assert!(channel.unacked().contains(&1));
+
+        deliver.delivery_tag = 2;
+        server.write_method(1, &deliver);
+        server.write_frame(protocol::FRAME_BODY, 1, b"oops");
+        match channel.next_event() {
+            Err(AmqpError::FrameTypeErr(protocol::FRAME_BODY, protocol::FRAME_HEADER)) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_server_cancel() {
+        let (mut channel, mut server) = Channel::for_test(4096);
+        consume(&mut channel, &mut server, true);
+        deliver(&mut server, 1, b"job");
+
+        let mut cancel = basic::Cancel::default();
+        cancel.consumer_tag = "ctag".to_string();
+        server.write_method(1, &cancel);
+
+        match channel.next_event().unwrap() {
+            ConsumerEvent::Delivery(delivery) => assert_eq!(delivery.body, &b"job"[..]),
+            other => panic!("{:?}", other),
+        }
+        assert!(channel.unacked().is_empty());
+        match channel.next_event().unwrap() {
+            ConsumerEvent::Cancelled(consumer_tag, consumer) => {
+                assert_eq!(consumer_tag, "ctag");
+                assert_eq!(consumer.queue, "jobs");
+            },
+            other => panic!("{:?}", other),
+        }
+        assert!(channel.consumers().is_empty());
+
+        let frames = server.read_frames();
+        assert_eq!(method_ids(&frames), vec![(60, 31)]);
+        let cancel_ok: basic::CancelOk = frames[0].to_method().unwrap();
+        assert_eq!(cancel_ok.consumer_tag, "ctag");
+    }
+
+    #[test]
+    fn test_settle() {
+        let (mut channel, mut server) = Channel::for_test(4096);
+        consume(&mut channel, &mut server, false);
+        for tag in 1..6 {
+            deliver(&mut server, tag, b"job");
+            channel.next_event().unwrap();
+        }
+        assert_eq!(channel.unacked().len(), 5);
+
+        channel.basic_ack(2, true).unwrap();
+        assert_eq!(channel.unacked().iter().cloned().collect::<Vec<_>>(), vec![3, 4, 5]);
+        channel.basic_nack(4, false, true).unwrap();
+        channel.basic_reject(3, false).unwrap();
+        assert_eq!(channel.unacked().iter().cloned().collect::<Vec<_>>(), vec![5]);
+        channel.basic_nack(u64::max_value(), true, false).unwrap();
+        assert!(channel.unacked().is_empty());
+
+        let frames = server.read_frames();
+        assert_eq!(method_ids(&frames), vec![(60, 80), (60, 120), (60, 90), (60, 120)]);
+        let nack: basic::Nack = frames[1].to_method().unwrap();
+        assert_eq!((nack.delivery_tag, nack.multiple, nack.requeue), (4, false, true));
+        let reject: basic::Reject = frames[2].to_method().unwrap();
+        assert_eq!((reject.delivery_tag, reject.requeue), (3, false));
+    }
+
+    #[test]
+    fn test_recover() {
+        let (mut channel, mut server) = Channel::for_test(4096);
+        consume(&mut channel, &mut server, false);
+        deliver(&mut server, 1, b"a");
+        deliver(&mut server, 2, b"b");
+        deliver(&mut server, 3, b"c");
+        channel.next_event().unwrap();
+        channel.basic_ack(1, false).unwrap();
+
+        // Deliveries 2 and 3 are queued on the channel by the time RecoverOk is read.
+        server.write_method(1, &basic::RecoverOk::default());
+        deliver(&mut server, 4, b"b");
+        channel.basic_recover(true).unwrap();
+        assert!(channel.unacked().is_empty());
+
+        match channel.next_event().unwrap() {
+            ConsumerEvent::Delivery(delivery) => assert_eq!(delivery.delivery_tag, 4),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(channel.unacked().iter().cloned().collect::<Vec<_>>(), vec![4]);
+    }
+
+    #[test]
+    fn test_server_close() {
+        let (mut channel, mut server) = Channel::for_test(4096);
+        let mut close = channel::Close::default();
+        close.reply_code = protocol::PRECONDITION_FAILED;
+        close.reply_text = "PRECONDITION_FAILED - inequivalent arg 'durable'".to_string();
+        close.class_id = 50;
+        close.method_id = 10;
+        server.write_method(1, &close);
+
+        let closed = |result: AmqpResult<()>| match result {
+            Err(AmqpError::ChannelClosed { reply_code, ref reply_text, class_id, method_id }) => {
+                assert_eq!((reply_code, class_id, method_id), (406, 50, 10));
+                assert!(reply_text.starts_with("PRECONDITION_FAILED"));
+            },
+            other => panic!("{:?}", other),
+        };
+        closed(channel.queue_declare(&queue::Declare::default()).map(|_| ()));
+        assert_eq!(method_ids(&server.read_frames()), vec![(50, 10), (20, 41)]);
+
+        assert!(!channel.is_open());
+        assert_eq!(channel.close_reason().unwrap().reply_code, 406);
+        closed(channel.basic_publish("", "jobs", &BasicProperties::default(), b"job"));
+        closed(channel.basic_ack(1, false));
+        channel.close().unwrap();
+        assert!(server.read_frames().is_empty());
+    }
+
+    #[test]
+    fn test_event_timeout() {
+        let (mut channel, mut server) = Channel::for_test(4096);
+        consume(&mut channel, &mut server, true);
+        match channel.next_event_timeout(Duration::from_millis(20)) {
+            Err(AmqpError::Timeout) => (),
+            other => panic!("{:?}", other),
+        }
+
+        let mut deliver = basic::Deliver::default();
+        deliver.consumer_tag = "ctag".to_string();
+        server.write_method(1, &deliver);
+        let header = ContentHeader::new(3, BasicProperties::default());
+        server.write_frame(protocol::FRAME_HEADER, 1, &header.se().unwrap());
+        let broker = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            server.write_frame(protocol::FRAME_BODY, 1, b"job");
+            server
+        });
+
+        match channel.next_event_timeout(Duration::from_millis(20)).unwrap() {
+            ConsumerEvent::Delivery(delivery) => assert_eq!(delivery.body, &b"job"[..]),
+            other => panic!("{:?}", other),
+        }
+        let _server = broker.join().unwrap();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_deliver_compressed() {
+        use compression::Compression;
+
+        let (mut channel, mut server) = Channel::for_test(4096);
+        channel.set_compressor(Some(Compressor::new(Compression::Gzip).max_size(8)));
+        consume(&mut channel, &mut server, true);
+
+        let mut deliver = basic::Deliver::default();
+        deliver.consumer_tag = "ctag".to_string();
+        let mut props = BasicProperties::default();
+        props.content_encoding = Some("gzip".to_string());
+        for body in &[Compression::Gzip.compress(b"job").unwrap(),
+                      Compression::Gzip.compress(b"too large a job").unwrap()] {
+            server.write_method(1, &deliver);
+            server.write_content(1, &props, body);
+        }
+
+        match channel.next_event().unwrap() {
+            ConsumerEvent::Delivery(delivery) => {
+                assert_eq!(delivery.body, &b"job"[..]);
+                assert_eq!(delivery.properties.content_encoding, None);
+            },
+            other => panic!("{:?}", other),
+        }
+        match channel.next_event().unwrap() {
+            ConsumerEvent::Delivery(mut delivery) => {
+                assert_eq!(delivery.properties.content_encoding, Some("gzip".to_string()));
+                match delivery.decompress(8) {
+                    Err(AmqpError::BodySizeErr(8)) => (),
+                    other => panic!("{:?}", other),
+                }
+            },
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tiny_frame_max() {
+        // The engine would refuse the broker's own frames at this size, so only the channel's
+        // view of frame_max is shrunk.
+        let (mut channel, mut server) = Channel::for_test(4096);
+        channel.frame_max = 9;
+        channel.basic_publish("", "jobs", &BasicProperties::default(), b"abc").unwrap();
+        let bodies: Vec<_> = server.read_frames().into_iter()
+                                   .filter(|f| f.ty == protocol::FRAME_BODY)
+                                   .map(|f| f.payload).collect();
+        assert_eq!(bodies, vec![&b"a"[..], &b"b"[..], &b"c"[..]]);
+    }
+
+    #[test]
+    fn test_publish_from_short_body() {
+        let (mut channel, mut server) = Channel::for_test(4096);
+        let props = BasicProperties::default();
+        match channel.basic_publish_from("", "jobs", &props, &mut &b"abc"[..], 10) {
+            Err(AmqpError::IoErr(_)) => (),
+            other => panic!("{:?}", other),
+        }
+        // Nothing had gone out, so nothing of it is sent and the channel stays usable.
+        assert!(server.read_frames().is_empty());
+        assert!(channel.is_open());
+
+        channel.basic_publish_from("", "jobs", &props, &mut &b"abc"[..], 3).unwrap();
+        let frames = server.read_frames();
+        assert_eq!(method_ids(&frames), vec![protocol::BASIC_PUBLISH]);
+        assert_eq!(frames[2].payload, &b"abc"[..]);
+    }
+
+    #[test]
+    fn test_publish_from_partly_sent() {
+        let (mut channel, mut server) = Channel::for_test(4096);
+        let body = vec![0u8; 256 * 1024];
+        let result = channel.basic_publish_from("", "jobs", &BasicProperties::default(),
+                                                &mut &body[..], body.len() as u64 + 1);
+        assert!(result.is_err());
+        assert!(!server.read_frames().is_empty());
+        assert_eq!(channel.close_reason().unwrap().reply_code, protocol::INTERNAL_ERROR);
+        match channel.basic_publish("", "jobs", &BasicProperties::default(), b"abc") {
+            Err(AmqpError::ChannelClosed { .. }) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+}
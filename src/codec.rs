@@ -0,0 +1,247 @@
+#[cfg(any(feature = "json", feature = "msgpack"))]
+use serde::Serialize;
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+
+use channel::Channel;
+use consumer::Delivery;
+use content::BasicProperties;
+use result::*;
+
+pub const OCTET_STREAM: &'static str = "application/octet-stream";
+pub const JSON: &'static str = "application/json";
+pub const JSON_UTF8: &'static str = "application/json; charset=utf-8";
+pub const MSGPACK: &'static str = "application/msgpack";
+
+pub trait BodyCodec {
+    fn content_type(&self) -> &'static str;
+
+    fn content_encoding(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+pub trait BodyEncode<T: ?Sized>: BodyCodec {
+    fn encode(&self, value: &T) -> AmqpResult<Vec<u8>>;
+}
+
+pub trait BodyDecode<T>: BodyCodec {
+    fn decode(&self, body: &[u8]) -> AmqpResult<T>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Raw;
+
+impl BodyCodec for Raw {
+    fn content_type(&self) -> &'static str {
+        OCTET_STREAM
+    }
+}
+
+impl BodyEncode<[u8]> for Raw {
+    fn encode(&self, value: &[u8]) -> AmqpResult<Vec<u8>> {
+        Ok(value.to_vec())
+    }
+}
+
+impl BodyDecode<Vec<u8>> for Raw {
+    fn decode(&self, body: &[u8]) -> AmqpResult<Vec<u8>> {
+        Ok(body.to_vec())
+    }
+}
+
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl BodyCodec for Json {
+    fn content_type(&self) -> &'static str {
+        JSON_UTF8
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: Serialize + ?Sized> BodyEncode<T> for Json {
+    fn encode(&self, value: &T) -> AmqpResult<Vec<u8>> {
+        ::serde_json::to_vec(value).map_err(|e| AmqpError::CodecErr(e.to_string()))
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: DeserializeOwned> BodyDecode<T> for Json {
+    fn decode(&self, body: &[u8]) -> AmqpResult<T> {
+        ::serde_json::from_slice(body).map_err(|e| AmqpError::CodecErr(e.to_string()))
+    }
+}
+
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl BodyCodec for MessagePack {
+    fn content_type(&self) -> &'static str {
+        MSGPACK
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl<T: Serialize + ?Sized> BodyEncode<T> for MessagePack {
+    fn encode(&self, value: &T) -> AmqpResult<Vec<u8>> {
+        ::rmp_serde::to_vec_named(value).map_err(|e| AmqpError::CodecErr(e.to_string()))
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl<T: DeserializeOwned> BodyDecode<T> for MessagePack {
+    fn decode(&self, body: &[u8]) -> AmqpResult<T> {
+        ::rmp_serde::from_slice(body).map_err(|e| AmqpError::CodecErr(e.to_string()))
+    }
+}
+
+impl Channel {
+    pub fn publish_with<C, T: ?Sized>(&mut self, codec: &C, exchange: &str, routing_key: &str,
+                                      props: &BasicProperties, value: &T) -> AmqpResult<()>
+        where C: BodyEncode<T>
+    {
+        let body = try!(codec.encode(value));
+        let mut props = props.clone();
+        props.content_type = Some(codec.content_type().to_string());
+        if props.content_encoding.is_none() {
+            props.content_encoding = codec.content_encoding().map(|e| e.to_string());
+        }
+        self.basic_publish(exchange, routing_key, &props, &body)
+    }
+
+    #[cfg(feature = "json")]
+    pub fn publish_json<T: Serialize + ?Sized>(&mut self, exchange: &str, routing_key: &str,
+                                               props: &BasicProperties, value: &T)
+                                               -> AmqpResult<()> {
+        self.publish_with(&Json, exchange, routing_key, props, value)
+    }
+
+    #[cfg(feature = "msgpack")]
+    pub fn publish_msgpack<T: Serialize + ?Sized>(&mut self, exchange: &str, routing_key: &str,
+                                                  props: &BasicProperties, value: &T)
+                                                  -> AmqpResult<()> {
+        self.publish_with(&MessagePack, exchange, routing_key, props, value)
+    }
+}
+
+impl Delivery {
+    pub fn content_type(&self) -> Option<&str> {
+        self.properties.content_type.as_ref().map(|ty| media_type(ty))
+    }
+
+    pub fn decode_with<C: BodyDecode<T>, T>(&self, codec: &C) -> AmqpResult<T> {
+        if self.content_type() != Some(media_type(codec.content_type())) {
+            return Err(AmqpError::ContentTypeErr(self.properties.content_type.clone()));
+        }
+        codec.decode(&self.body)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn decode<T: DeserializeOwned>(&self) -> AmqpResult<T> {
+        match self.content_type() {
+            #[cfg(feature = "json")]
+            Some(JSON) => Json.decode(&self.body),
+            #[cfg(feature = "msgpack")]
+            Some(MSGPACK) => MessagePack.decode(&self.body),
+            _ => Err(AmqpError::ContentTypeErr(self.properties.content_type.clone())),
+        }
+    }
+}
+
+// "application/json; charset=utf-8" names the same codec as "application/json".
+fn media_type(content_type: &str) -> &str {
+    content_type.split(';').next().unwrap_or("").trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delivery(content_type: Option<&str>, body: &[u8]) -> Delivery {
+        let mut properties = BasicProperties::default();
+        properties.content_type = content_type.map(|ty| ty.to_string());
+        Delivery::for_test(properties, body)
+    }
+
+    #[test]
+    fn test_raw() {
+        let d = delivery(Some(OCTET_STREAM), b"raw");
+        assert_eq!(d.decode_with(&Raw).unwrap(), b"raw".to_vec());
+
+        let d = delivery(Some("text/plain"), b"raw");
+        match d.decode_with(&Raw) {
+            Err(AmqpError::ContentTypeErr(Some(ref ty))) => assert_eq!(ty, "text/plain"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    struct Encoded;
+
+    impl BodyCodec for Encoded {
+        fn content_type(&self) -> &'static str {
+            OCTET_STREAM
+        }
+
+        fn content_encoding(&self) -> Option<&'static str> {
+            Some("x-custom")
+        }
+    }
+
+    impl BodyEncode<[u8]> for Encoded {
+        fn encode(&self, value: &[u8]) -> AmqpResult<Vec<u8>> {
+            Ok(value.to_vec())
+        }
+    }
+
+    #[test]
+    fn test_publish_with() {
+        let (mut channel, mut server) = Channel::for_test(4096);
+        let mut props = BasicProperties::default();
+        channel.publish_with(&Encoded, "", "jobs", &props, &b"a"[..]).unwrap();
+        props.content_encoding = Some("gzip".to_string());
+        channel.publish_with(&Encoded, "", "jobs", &props, &b"b"[..]).unwrap();
+
+        let encodings: Vec<_> = server.read_frames().into_iter()
+                                      .filter(|f| f.ty == ::protocol::FRAME_HEADER)
+                                      .map(|f| f.to_content_header().unwrap())
+                                      .map(|h| h.properties.content_encoding).collect();
+        assert_eq!(encodings, vec![Some("x-custom".to_string()), Some("gzip".to_string())]);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json() {
+        assert_eq!(Json.content_encoding(), None);
+        let body = Json.encode(&vec![1, 2, 3]).unwrap();
+        let d = delivery(Some(JSON_UTF8), &body);
+        assert_eq!(d.decode::<Vec<u32>>().unwrap(), vec![1, 2, 3]);
+        assert_eq!(d.decode_with::<_, Vec<u32>>(&Json).unwrap(), vec![1, 2, 3]);
+        let d = delivery(Some(JSON), &body);
+        assert_eq!(d.decode_with::<_, Vec<u32>>(&Json).unwrap(), vec![1, 2, 3]);
+
+        let d = delivery(None, &body);
+        match d.decode::<Vec<u32>>() {
+            Err(AmqpError::ContentTypeErr(None)) => (),
+            other => panic!("{:?}", other),
+        }
+
+        let d = delivery(Some(JSON), b"{");
+        match d.decode::<Vec<u32>>() {
+            Err(AmqpError::CodecErr(_)) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack() {
+        let body = MessagePack.encode(&("a".to_string(), 1u8)).unwrap();
+        let d = delivery(Some(MSGPACK), &body);
+        assert_eq!(d.decode::<(String, u8)>().unwrap(), ("a".to_string(), 1));
+    }
+}
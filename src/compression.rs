@@ -0,0 +1,235 @@
+#[cfg(any(feature = "gzip", feature = "deflate"))]
+use flate2;
+
+#[cfg(any(feature = "gzip", feature = "deflate", feature = "zstd"))]
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use bytes::Bytes;
+
+use consumer::Delivery;
+use content::BasicProperties;
+use result::*;
+
+const DEFAULT_THRESHOLD: usize = 1024;
+pub const DEFAULT_MAX_SIZE: usize = 128 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "deflate")]
+    Deflate,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => "gzip",
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => "deflate",
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    #[allow(unused_variables)]
+    pub fn compress(&self, data: &[u8]) -> AmqpResult<Vec<u8>> {
+        match *self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(),
+                                                                flate2::Compression::default());
+                try!(encoder.write_all(data));
+                Ok(try!(encoder.finish()))
+            },
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => {
+                let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(),
+                                                                  flate2::Compression::default());
+                try!(encoder.write_all(data));
+                Ok(try!(encoder.finish()))
+            },
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(try!(::zstd::stream::encode_all(data, 0))),
+        }
+    }
+
+    // A few bytes can inflate to gigabytes, so the output stops at max_size.
+    #[allow(unused_variables)]
+    pub fn decompress(&self, data: &[u8], max_size: usize) -> AmqpResult<Vec<u8>> {
+        match *self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => read_limited(flate2::read::GzDecoder::new(data), max_size),
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => read_limited(flate2::read::ZlibDecoder::new(data), max_size),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                read_limited(try!(::zstd::stream::read::Decoder::new(data)), max_size)
+            },
+        }
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "deflate", feature = "zstd"))]
+fn read_limited<R: Read>(reader: R, max_size: usize) -> AmqpResult<Vec<u8>> {
+    let mut vec = Vec::new();
+    try!(reader.take(max_size as u64 + 1).read_to_end(&mut vec));
+    if vec.len() > max_size {
+        return Err(AmqpError::BodySizeErr(max_size));
+    }
+    Ok(vec)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Compressor {
+    compression: Compression,
+    threshold: usize,
+    max_size: usize,
+}
+
+impl Compressor {
+    pub fn new(compression: Compression) -> Compressor {
+        Compressor {
+            compression: compression,
+            threshold: DEFAULT_THRESHOLD,
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+
+    pub fn threshold(mut self, bytes: usize) -> Compressor {
+        self.threshold = bytes;
+        self
+    }
+
+    pub fn max_size(mut self, bytes: usize) -> Compressor {
+        self.max_size = bytes;
+        self
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub fn decompress(&self, delivery: &mut Delivery) -> AmqpResult<bool> {
+        delivery.decompress(self.max_size)
+    }
+
+    pub fn compress(&self, props: &mut BasicProperties, body: &[u8])
+                    -> AmqpResult<Option<Vec<u8>>> {
+        if body.len() < self.threshold {
+            return Ok(None);
+        }
+        if !props.content_encoding.as_ref().map_or(true, |e| is_identity(e)) {
+            return Ok(None);
+        }
+
+        let compressed = try!(self.compression.compress(body));
+        props.content_encoding = Some(self.compression.as_str().to_string());
+        Ok(Some(compressed))
+    }
+}
+
+impl FromStr for Compression {
+    type Err = AmqpError;
+
+    fn from_str(s: &str) -> AmqpResult<Compression> {
+        match s.trim() {
+            #[cfg(feature = "gzip")]
+            "gzip" | "x-gzip" => Ok(Compression::Gzip),
+            #[cfg(feature = "deflate")]
+            "deflate" => Ok(Compression::Deflate),
+            #[cfg(feature = "zstd")]
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(AmqpError::ContentEncodingErr(s.to_string())),
+        }
+    }
+}
+
+impl Delivery {
+    pub fn decompress(&mut self, max_size: usize) -> AmqpResult<bool> {
+        let compression = match self.properties.content_encoding {
+            Some(ref encoding) => match encoding.parse::<Compression>() {
+                Ok(compression) => compression,
+                Err(_) => return Ok(false),
+            },
+            None => return Ok(false),
+        };
+
+        self.body = Bytes::from(try!(compression.decompress(&self.body, max_size)));
+        self.properties.content_encoding = None;
+        Ok(true)
+    }
+}
+
+// Any other encoding means the body is already encoded, and it is sent as is.
+fn is_identity(encoding: &str) -> bool {
+    let encoding = encoding.trim();
+    encoding.is_empty() || encoding.eq_ignore_ascii_case("identity")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(feature = "gzip", feature = "deflate", feature = "zstd"))]
+    fn round_trip(compression: Compression) {
+        let body = vec![b'a'; 4096];
+        let compressor = Compressor::new(compression).threshold(100);
+        assert_eq!(compression.as_str().parse::<Compression>().unwrap(), compression);
+
+        let mut props = BasicProperties::default();
+        assert_eq!(compressor.compress(&mut props, &body[..10]).unwrap(), None);
+        assert_eq!(props.content_encoding, None);
+
+        let compressed = compressor.compress(&mut props, &body).unwrap().unwrap();
+        assert!(compressed.len() < body.len());
+        assert_eq!(props.content_encoding, Some(compression.as_str().to_string()));
+
+        let mut delivery = Delivery::for_test(props, &compressed);
+        match delivery.clone().decompress(body.len() - 1) {
+            Err(AmqpError::BodySizeErr(4095)) => (),
+            other => panic!("{:?}", other),
+        }
+        assert!(compressor.decompress(&mut delivery).unwrap());
+        assert_eq!(delivery.body, body);
+        assert_eq!(delivery.properties.content_encoding, None);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip() {
+        round_trip(Compression::Gzip);
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn test_deflate() {
+        round_trip(Compression::Deflate);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd() {
+        round_trip(Compression::Zstd);
+    }
+
+    #[test]
+    fn test_passthrough() {
+        let mut props = BasicProperties::default();
+        props.content_encoding = Some("br".to_string());
+        let mut delivery = Delivery::for_test(props, b"body");
+        assert!(!delivery.decompress(DEFAULT_MAX_SIZE).unwrap());
+        assert_eq!(delivery.body, b"body".to_vec());
+        match "br".parse::<Compression>() {
+            Err(AmqpError::ContentEncodingErr(ref encoding)) if encoding == "br" => (),
+            other => panic!("{:?}", other),
+        }
+        assert!(is_identity("Identity"));
+        assert!(!is_identity("utf-8"));
+        assert!(!is_identity("br"));
+    }
+}
@@ -1,14 +1,19 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::u16;
+
+use channel::{Channel, CloseReason};
 use conf::Conf;
-use frame::Frame;
-use protocol::{self, connection};
 use result::*;
+use socket::Socket;
 use transport::Transport;
 use types::*;
-use types::FieldValue::*;
 
 pub struct Connection {
-    conf: Conf,
-    tx: Transport,
+    tx: Rc<RefCell<Transport>>,
+    channel_max: u16,
+    frame_max: u32,
+    next_channel: u16,
 
     pub server_props: Table,
 }
@@ -16,79 +21,111 @@ pub struct Connection {
 impl Connection {
     pub fn new(conf: Conf) -> Connection {
         let tx = Transport::new(&conf);
+        Connection::with_transport(tx)
+    }
+
+    pub fn with_socket(conf: Conf, socket: Box<Socket>) -> Connection {
+        Connection::with_transport(Transport::with_socket(&conf, socket))
+    }
+
+    fn with_transport(tx: Transport) -> Connection {
         Connection {
-            conf: conf,
-            tx: tx,
+            tx: Rc::new(RefCell::new(tx)),
+            channel_max: 0,
+            frame_max: 0,
+            next_channel: 1,
 
             server_props: Table::new(),
         }
     }
 
     pub fn start(&mut self) -> AmqpResult<()> {
-        try!(self.tx.send_header());
-
-        let frame = try!(self.tx.recv());
-        let start: connection::Start = try!(frame.to_method());
-        self.server_props = start.server_properties.clone();
-
-        let mut start_ok = connection::StartOk::default();
-        start_ok.client_properties = self.client_props();
-        start_ok.mechanism = "PLAIN".to_string();
-        start_ok.response = self.start_ok_response();
-        start_ok.locale = "en_US".to_string();
-        try!(self.tx.send(Frame::from_method(0, &start_ok).unwrap()));
-
-        let frame = try!(self.tx.recv());
-        let tune: connection::Tune = try!(frame.to_method());
-
-        let mut tune_ok = connection::TuneOk::default();
-        tune_ok.channel_max = tune.channel_max;
-        tune_ok.frame_max = tune.frame_max;
-        tune_ok.heartbeat = tune.heartbeat;
-        try!(self.tx.send(Frame::from_method(0, &tune_ok).unwrap()));
-
-        let open = connection::Open::default();
-        try!(self.tx.send(Frame::from_method(0, &open).unwrap()));
-
-        let frame = try!(self.tx.recv());
-        let open_ok: connection::OpenOk = try!(frame.to_method());
+        let mut tx = self.tx.borrow_mut();
+        try!(tx.open());
 
+        let engine = tx.engine();
+        self.server_props = engine.server_props().clone();
+        self.channel_max = engine.channel_max();
+        self.frame_max = engine.frame_max();
         Ok(())
     }
 
     pub fn close(&mut self) -> AmqpResult<()> {
-        let mut close = connection::Close::default();
-        close.reply_code = protocol::REPLY_SUCCESS as u16;
-        close.reply_text = "OK".to_string();
-        try!(self.tx.send(Frame::from_method(0, &close).unwrap()));
+        self.tx.borrow_mut().close()
+    }
 
-        let frame = try!(self.tx.recv());
-        let close_ok: connection::CloseOk = try!(frame.to_method());
+    // Set once the broker has closed the connection, e.g. with CONNECTION_FORCED.
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        self.tx.borrow().close_reason().cloned()
+    }
+
+    pub fn open_channel(&mut self) -> AmqpResult<Channel> {
+        let channel_max = if self.channel_max == 0 { u16::MAX } else { self.channel_max };
+        if self.next_channel == 0 || self.next_channel > channel_max {
+            return Err(AmqpError::ChannelMaxErr(channel_max));
+        }
 
-        self.tx.close()
+        let mut channel = Channel::new(self.next_channel, self.tx.clone(), self.frame_max);
+        self.next_channel = self.next_channel.wrapping_add(1);
+        try!(channel.open());
+        Ok(channel)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::{channel, connection};
+    use socket::{MemorySocket, Peer};
+    use std::io::Read;
+
+    #[test]
+    fn test_start() {
+        let (client, mut server) = MemorySocket::pair();
+        let mut tune = connection::Tune::default();
+        tune.channel_max = 1;
+        tune.frame_max = 4096;
+        server.write_method(0, &connection::Start::default());
+        server.write_method(0, &tune);
+        server.write_method(0, &connection::OpenOk::default());
+        server.write_method(1, &channel::OpenOk::default());
+
+        let mut conn = Connection::with_socket(Conf::default(), Box::new(client));
+        conn.start().unwrap();
+        assert_eq!(conn.frame_max, 4096);
+
+        let channel = conn.open_channel().unwrap();
+        assert_eq!(channel.id(), 1);
+        match conn.open_channel() {
+            Err(AmqpError::ChannelMaxErr(1)) => (),
+            other => panic!("{:?}", other.map(|c| c.id())),
+        }
+        // Only a close_ok that arrives after the close ends it.
+        server.write_method(0, &connection::CloseOk::default());
+        conn.close().unwrap();
 
-    fn client_props(&self) -> Table {
-        let mut client_props = Table::new();
-        client_props.insert("product".to_string(), LongString(b"rust-amqp".to_vec()));
-        client_props.insert("platform".to_string(), LongString(b"rust".to_vec()));
-        client_props.insert("version".to_string(), LongString(b"0.0.1".to_vec()));
-        client_props.insert("information".to_string(),
-                            LongString(b"https://github.com/zbaitu/rust-amqp-client".to_vec()));
-
-        let mut caps = Table::new();
-        caps.insert("publisher_confirms".to_string(), Bool(true));
-        caps.insert("exchange_exchange_bindings".to_string(), Bool(true));
-        caps.insert("basic.nack".to_string(), Bool(true));
-        caps.insert("consumer_cancel_notify".to_string(), Bool(true));
-        caps.insert("connection.blocked".to_string(), Bool(true));
-        caps.insert("authentication_failure_close".to_string(), Bool(true));
-        client_props.insert("capabilities".to_string(), FieldTable(caps));
-
-        client_props
+        let mut sent = Vec::new();
+        server.read_to_end(&mut sent).unwrap();
+        assert!(sent.starts_with(&::protocol::PROT_HEADER));
     }
 
-    fn start_ok_response(&self) -> Longstr {
-        format!("\0{}\0{}", self.conf.user, self.conf.password).into_bytes()
+    #[test]
+    fn test_server_close() {
+        let (client, mut server) = MemorySocket::pair();
+        let mut close = connection::Close::default();
+        close.reply_code = 320;
+        close.reply_text = "CONNECTION_FORCED".to_string();
+        server.write_method(0, &connection::Start::default());
+        server.write_method(0, &connection::Tune::default());
+        server.write_method(0, &connection::OpenOk::default());
+        server.write_method(0, &close);
+
+        let mut conn = Connection::with_socket(Conf::default(), Box::new(client));
+        conn.start().unwrap();
+        match conn.open_channel() {
+            Err(AmqpError::ConnectionClosed) => (),
+            other => panic!("{:?}", other.map(|c| c.id())),
+        }
+        assert_eq!(conn.close_reason().unwrap().reply_code, 320);
     }
 }
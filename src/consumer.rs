@@ -0,0 +1,81 @@
+use bytes::Bytes;
+
+use content::BasicProperties;
+use stream;
+use types::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Consumer {
+    pub queue: Shortstr,
+    pub consumer_tag: Shortstr,
+    pub no_local: bool,
+    pub no_ack: bool,
+    pub exclusive: bool,
+    pub arguments: Table,
+}
+
+impl Consumer {
+    pub fn new(queue: &str) -> Consumer {
+        Consumer {
+            queue: queue.to_string(),
+            consumer_tag: String::new(),
+            no_local: false,
+            no_ack: false,
+            exclusive: false,
+            arguments: Table::new(),
+        }
+    }
+}
+
+// A compressed body is decompressed on arrival. One that fails to, being corrupt or larger
+// than the size limit, is delivered untouched with its content_encoding still set; calling
+// decompress on it returns the error, and the message can be rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delivery {
+    pub consumer_tag: Shortstr,
+    pub delivery_tag: Longlong,
+    pub redelivered: bool,
+    pub exchange: Shortstr,
+    pub routing_key: Shortstr,
+    pub properties: BasicProperties,
+    pub body: Bytes,
+}
+
+impl Delivery {
+    pub fn header(&self, key: &str) -> Option<&FieldValue> {
+        self.properties.headers.as_ref().and_then(|headers| headers.get(key))
+    }
+
+    pub fn stream_offset(&self) -> Option<u64> {
+        self.header(stream::STREAM_OFFSET).and_then(FieldValue::as_u64)
+    }
+
+    #[cfg(test)]
+    pub fn for_test(properties: BasicProperties, body: &[u8]) -> Delivery {
+        Delivery {
+            consumer_tag: String::new(),
+            delivery_tag: 1,
+            redelivered: false,
+            exchange: String::new(),
+            routing_key: String::new(),
+            properties: properties,
+            body: Bytes::from(body.to_vec()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnedMessage {
+    pub reply_code: Short,
+    pub reply_text: Shortstr,
+    pub exchange: Shortstr,
+    pub routing_key: Shortstr,
+    pub properties: BasicProperties,
+    pub body: Bytes,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsumerEvent {
+    Delivery(Delivery),
+    Cancelled(Shortstr, Consumer),
+}
@@ -0,0 +1,252 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use std::io::{Read, Write};
+use std::u8;
+
+use method;
+use result::*;
+use types::*;
+
+const CONTENT_TYPE: u16 = 1 << 15;
+const CONTENT_ENCODING: u16 = 1 << 14;
+const HEADERS: u16 = 1 << 13;
+const DELIVERY_MODE: u16 = 1 << 12;
+const PRIORITY: u16 = 1 << 11;
+const CORRELATION_ID: u16 = 1 << 10;
+const REPLY_TO: u16 = 1 << 9;
+const EXPIRATION: u16 = 1 << 8;
+const MESSAGE_ID: u16 = 1 << 7;
+const TIMESTAMP: u16 = 1 << 6;
+const TY: u16 = 1 << 5;
+const USER_ID: u16 = 1 << 4;
+const APP_ID: u16 = 1 << 3;
+const CLUSTER_ID: u16 = 1 << 2;
+
+pub const BASIC_CLASS_ID: Short = 60;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BasicProperties {
+    pub content_type: Option<Shortstr>,
+    pub content_encoding: Option<Shortstr>,
+    pub headers: Option<Table>,
+    pub delivery_mode: Option<Octet>,
+    pub priority: Option<Octet>,
+    pub correlation_id: Option<Shortstr>,
+    pub reply_to: Option<Shortstr>,
+    pub expiration: Option<Shortstr>,
+    pub message_id: Option<Shortstr>,
+    pub timestamp: Option<Longlong>,
+    pub ty: Option<Shortstr>,
+    pub user_id: Option<Shortstr>,
+    pub app_id: Option<Shortstr>,
+    pub cluster_id: Option<Shortstr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentHeader {
+    pub class_id: Short,
+    pub body_size: Longlong,
+    pub properties: BasicProperties,
+}
+
+impl ContentHeader {
+    pub fn new(body_size: Longlong, properties: BasicProperties) -> ContentHeader {
+        ContentHeader {
+            class_id: BASIC_CLASS_ID,
+            body_size: body_size,
+            properties: properties,
+        }
+    }
+
+    pub fn se(&self) -> AmqpResult<Vec<u8>> {
+        let mut data = Vec::new();
+        try!(data.write_u16::<BigEndian>(self.class_id));
+        try!(data.write_u16::<BigEndian>(0));
+        try!(data.write_u64::<BigEndian>(self.body_size));
+        try!(self.properties.se_into(&mut data));
+        Ok(data)
+    }
+
+    pub fn de(mut v: &[u8]) -> AmqpResult<ContentHeader> {
+        let data = &mut v;
+        let class_id = try!(data.read_u16::<BigEndian>());
+        try!(data.read_u16::<BigEndian>());
+        let body_size = try!(data.read_u64::<BigEndian>());
+        let properties = try!(BasicProperties::de_from(data));
+
+        let header = ContentHeader {
+            class_id: class_id,
+            body_size: body_size,
+            properties: properties,
+        };
+        Ok(header)
+    }
+}
+
+impl BasicProperties {
+    fn flags(&self) -> u16 {
+        let mut flags = 0;
+        if self.content_type.is_some() { flags |= CONTENT_TYPE; }
+        if self.content_encoding.is_some() { flags |= CONTENT_ENCODING; }
+        if self.headers.is_some() { flags |= HEADERS; }
+        if self.delivery_mode.is_some() { flags |= DELIVERY_MODE; }
+        if self.priority.is_some() { flags |= PRIORITY; }
+        if self.correlation_id.is_some() { flags |= CORRELATION_ID; }
+        if self.reply_to.is_some() { flags |= REPLY_TO; }
+        if self.expiration.is_some() { flags |= EXPIRATION; }
+        if self.message_id.is_some() { flags |= MESSAGE_ID; }
+        if self.timestamp.is_some() { flags |= TIMESTAMP; }
+        if self.ty.is_some() { flags |= TY; }
+        if self.user_id.is_some() { flags |= USER_ID; }
+        if self.app_id.is_some() { flags |= APP_ID; }
+        if self.cluster_id.is_some() { flags |= CLUSTER_ID; }
+        flags
+    }
+
+    fn se_into(&self, data: &mut Vec<u8>) -> AmqpResult<()> {
+        try!(data.write_u16::<BigEndian>(self.flags()));
+
+        try!(write_opt_short_str(data, &self.content_type));
+        try!(write_opt_short_str(data, &self.content_encoding));
+        if let Some(ref headers) = self.headers {
+            try!(data.write_all(&try!(method::se_table(headers))));
+        }
+        if let Some(delivery_mode) = self.delivery_mode {
+            try!(data.write_u8(delivery_mode));
+        }
+        if let Some(priority) = self.priority {
+            try!(data.write_u8(priority));
+        }
+        try!(write_opt_short_str(data, &self.correlation_id));
+        try!(write_opt_short_str(data, &self.reply_to));
+        try!(write_opt_short_str(data, &self.expiration));
+        try!(write_opt_short_str(data, &self.message_id));
+        if let Some(timestamp) = self.timestamp {
+            try!(data.write_u64::<BigEndian>(timestamp));
+        }
+        try!(write_opt_short_str(data, &self.ty));
+        try!(write_opt_short_str(data, &self.user_id));
+        try!(write_opt_short_str(data, &self.app_id));
+        try!(write_opt_short_str(data, &self.cluster_id));
+        Ok(())
+    }
+
+    fn de_from(data: &mut &[u8]) -> AmqpResult<BasicProperties> {
+        let flags = try!(data.read_u16::<BigEndian>());
+        let mut props = BasicProperties::default();
+
+        if flags & CONTENT_TYPE != 0 {
+            props.content_type = Some(try!(read_short_str(data)));
+        }
+        if flags & CONTENT_ENCODING != 0 {
+            props.content_encoding = Some(try!(read_short_str(data)));
+        }
+        if flags & HEADERS != 0 {
+            props.headers = Some(try!(method::de_table(data)));
+        }
+        if flags & DELIVERY_MODE != 0 {
+            props.delivery_mode = Some(try!(data.read_u8()));
+        }
+        if flags & PRIORITY != 0 {
+            props.priority = Some(try!(data.read_u8()));
+        }
+        if flags & CORRELATION_ID != 0 {
+            props.correlation_id = Some(try!(read_short_str(data)));
+        }
+        if flags & REPLY_TO != 0 {
+            props.reply_to = Some(try!(read_short_str(data)));
+        }
+        if flags & EXPIRATION != 0 {
+            props.expiration = Some(try!(read_short_str(data)));
+        }
+        if flags & MESSAGE_ID != 0 {
+            props.message_id = Some(try!(read_short_str(data)));
+        }
+        if flags & TIMESTAMP != 0 {
+            props.timestamp = Some(try!(data.read_u64::<BigEndian>()));
+        }
+        if flags & TY != 0 {
+            props.ty = Some(try!(read_short_str(data)));
+        }
+        if flags & USER_ID != 0 {
+            props.user_id = Some(try!(read_short_str(data)));
+        }
+        if flags & APP_ID != 0 {
+            props.app_id = Some(try!(read_short_str(data)));
+        }
+        if flags & CLUSTER_ID != 0 {
+            props.cluster_id = Some(try!(read_short_str(data)));
+        }
+
+        Ok(props)
+    }
+}
+
+fn write_opt_short_str(data: &mut Vec<u8>, v: &Option<Shortstr>) -> AmqpResult<()> {
+    if let Some(ref s) = *v {
+        let len = s.len();
+        if len > u8::MAX as usize {
+            return Err(AmqpError::ShortStrTooLong(len));
+        }
+        try!(data.write_u8(len as u8));
+        try!(data.write_all(s.as_bytes()));
+    }
+    Ok(())
+}
+
+fn read_short_str(data: &mut &[u8]) -> AmqpResult<Shortstr> {
+    let len = try!(data.read_u8()) as usize;
+    let mut vec = vec![0u8; len];
+    try!(data.read_exact(&mut vec));
+    Ok(try!(String::from_utf8(vec)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::FieldValue::*;
+
+    #[test]
+    fn test_empty() {
+        let a = ContentHeader::new(0, BasicProperties::default());
+        let vec = a.se().unwrap();
+        assert_eq!(vec.len(), 14);
+
+        let b = ContentHeader::de(&vec).unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_full() {
+        let mut headers = Table::new();
+        headers.insert("a".to_string(), Bool(true));
+        headers.insert("b".to_string(), LongString(b"long string".to_vec()));
+
+        let props = BasicProperties {
+            content_type: Some("application/json".to_string()),
+            content_encoding: Some("gzip".to_string()),
+            headers: Some(headers),
+            delivery_mode: Some(2),
+            priority: Some(5),
+            correlation_id: Some("correlation".to_string()),
+            reply_to: Some("amq.rabbitmq.reply-to".to_string()),
+            expiration: Some("60000".to_string()),
+            message_id: Some("message".to_string()),
+            timestamp: Some(1234),
+            ty: Some("type".to_string()),
+            user_id: Some("guest".to_string()),
+            app_id: Some("app".to_string()),
+            cluster_id: Some("cluster".to_string()),
+        };
+        let a = ContentHeader::new(4321, props);
+        println!("{:#?}", a);
+
+        let vec = a.se().unwrap();
+        println!("{:?}", vec);
+
+        let b = ContentHeader::de(&vec).unwrap();
+        println!("{:#?}", b);
+
+        assert_eq!(a, b);
+    }
+}
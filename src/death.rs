@@ -0,0 +1,215 @@
+use std::str::FromStr;
+
+use consumer::Delivery;
+use result::*;
+use types::*;
+use types::FieldValue::*;
+
+pub const X_DEATH: &'static str = "x-death";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeathReason {
+    Rejected,
+    Expired,
+    Maxlen,
+    DeliveryLimit,
+}
+
+impl FromStr for DeathReason {
+    type Err = AmqpError;
+
+    fn from_str(s: &str) -> AmqpResult<DeathReason> {
+        match s {
+            "rejected" => Ok(DeathReason::Rejected),
+            "expired" => Ok(DeathReason::Expired),
+            "maxlen" => Ok(DeathReason::Maxlen),
+            "delivery_limit" => Ok(DeathReason::DeliveryLimit),
+            _ => Err(header_err("reason")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Death {
+    pub count: u64,
+    pub reason: DeathReason,
+    pub queue: String,
+    pub exchange: String,
+    pub routing_keys: Vec<String>,
+    pub time: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeathHistory {
+    pub deaths: Vec<Death>,
+}
+
+impl DeathHistory {
+    pub fn from_headers(headers: &Table) -> AmqpResult<DeathHistory> {
+        let entries = match headers.get(X_DEATH) {
+            Some(&FieldArray(ref entries)) => entries,
+            Some(_) => return Err(header_err("")),
+            None => return Ok(DeathHistory::default()),
+        };
+
+        let mut deaths = Vec::with_capacity(entries.len());
+        for entry in entries {
+            match *entry {
+                FieldTable(ref table) => deaths.push(try!(parse_death(table))),
+                _ => return Err(header_err("")),
+            }
+        }
+        Ok(DeathHistory { deaths: deaths })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deaths.is_empty()
+    }
+
+    pub fn latest(&self) -> Option<&Death> {
+        self.deaths.first()
+    }
+
+    pub fn total_count(&self) -> u64 {
+        self.deaths.iter().map(|death| death.count).sum()
+    }
+
+    pub fn count_for_queue(&self, queue: &str) -> u64 {
+        self.deaths.iter().filter(|death| death.queue == queue).map(|death| death.count).sum()
+    }
+
+    pub fn count_for_reason(&self, reason: DeathReason) -> u64 {
+        self.deaths.iter().filter(|death| death.reason == reason).map(|death| death.count).sum()
+    }
+}
+
+impl Delivery {
+    pub fn death_history(&self) -> AmqpResult<DeathHistory> {
+        match self.properties.headers {
+            Some(ref headers) => DeathHistory::from_headers(headers),
+            None => Ok(DeathHistory::default()),
+        }
+    }
+}
+
+fn parse_death(table: &Table) -> AmqpResult<Death> {
+    let reason = try!(try!(get_str(table, "reason")).parse());
+
+    let routing_keys = match table.get("routing-keys") {
+        Some(&FieldArray(ref keys)) => {
+            let mut routing_keys = Vec::with_capacity(keys.len());
+            for key in keys {
+                match key.as_str() {
+                    Some(key) => routing_keys.push(key.to_string()),
+                    None => return Err(header_err("routing-keys")),
+                }
+            }
+            routing_keys
+        },
+        None => Vec::new(),
+        Some(_) => return Err(header_err("routing-keys")),
+    };
+
+    let time = match table.get("time") {
+        Some(&Timestamp(time)) => Some(time),
+        None => None,
+        Some(_) => return Err(header_err("time")),
+    };
+
+    let death = Death {
+        count: try!(get_u64(table, "count")),
+        reason: reason,
+        queue: try!(get_str(table, "queue")),
+        exchange: try!(get_str(table, "exchange")),
+        routing_keys: routing_keys,
+        time: time,
+    };
+    Ok(death)
+}
+
+fn get_str(table: &Table, key: &str) -> AmqpResult<String> {
+    table.get_str(key).map(|s| s.to_string()).ok_or_else(|| header_err(key))
+}
+
+fn get_u64(table: &Table, key: &str) -> AmqpResult<u64> {
+    table.get_u64(key).ok_or_else(|| header_err(key))
+}
+
+fn header_err(field: &str) -> AmqpError {
+    if field.is_empty() {
+        AmqpError::HeaderErr(X_DEATH.to_string())
+    } else {
+        AmqpError::HeaderErr(format!("{}.{}", X_DEATH, field))
+    }
+}
+
+#[cfg(test)]
+pub fn entry(queue: &str, reason: &str, count: u64) -> FieldValue {
+    let mut table = Table::new();
+    table.insert("count".to_string(), LongLongUint(count));
+    table.insert("reason".to_string(), LongString(reason.as_bytes().to_vec()));
+    table.insert("queue".to_string(), LongString(queue.as_bytes().to_vec()));
+    table.insert("exchange".to_string(), LongString(b"work".to_vec()));
+    table.insert("routing-keys".to_string(), FieldArray(vec![LongString(b"job".to_vec())]));
+    table.insert("time".to_string(), Timestamp(1234));
+    FieldTable(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use content::BasicProperties;
+
+    #[test]
+    fn test_history() {
+        let mut headers = Table::new();
+        headers.insert(X_DEATH.to_string(),
+                       FieldArray(vec![entry("retry-5s", "expired", 2),
+                                       entry("work", "rejected", 3)]));
+
+        let history = DeathHistory::from_headers(&headers).unwrap();
+        assert_eq!(history.deaths.len(), 2);
+        assert_eq!(history.total_count(), 5);
+        assert_eq!(history.count_for_queue("work"), 3);
+        assert_eq!(history.count_for_reason(DeathReason::Expired), 2);
+
+        let latest = history.latest().unwrap();
+        assert_eq!(latest.queue, "retry-5s");
+        assert_eq!(latest.exchange, "work");
+        assert_eq!(latest.routing_keys, vec!["job".to_string()]);
+        assert_eq!(latest.time, Some(1234));
+    }
+
+    #[test]
+    fn test_delivery() {
+        let mut headers = Table::new();
+        headers.insert(X_DEATH.to_string(), FieldArray(vec![entry("work", "rejected", 1)]));
+        let mut properties = BasicProperties::default();
+        properties.headers = Some(headers);
+
+        let history = Delivery::for_test(properties, b"").death_history().unwrap();
+        assert_eq!(history.count_for_reason(DeathReason::Rejected), 1);
+        let history = Delivery::for_test(BasicProperties::default(), b"").death_history().unwrap();
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn test_absent() {
+        let history = DeathHistory::from_headers(&Table::new()).unwrap();
+        assert!(history.is_empty());
+        assert_eq!(history.total_count(), 0);
+    }
+
+    #[test]
+    fn test_reason() {
+        assert_eq!("delivery_limit".parse::<DeathReason>().unwrap(), DeathReason::DeliveryLimit);
+        assert!("Expired".parse::<DeathReason>().is_err());
+    }
+
+    #[test]
+    fn test_invalid_reason() {
+        let mut headers = Table::new();
+        headers.insert(X_DEATH.to_string(), FieldArray(vec![entry("work", "unknown", 1)]));
+        assert!(DeathHistory::from_headers(&headers).is_err());
+    }
+}
@@ -0,0 +1,497 @@
+use bytes::BytesMut;
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use channel::CloseReason;
+use conf::Conf;
+use frame::{self, Frame};
+use method::Method;
+use protocol::{self, connection, CONNECTION_CLOSE, CONNECTION_CLOSE_OK};
+use result::*;
+use types::*;
+use types::FieldValue::*;
+
+// Offered back when the broker leaves frame_max unlimited, so no peer can make a frame, and
+// with it the input buffer, grow without bound.
+const DEFAULT_FRAME_MAX: u32 = 128 * 1024;
+
+// Drivers read the socket in chunks of at most this size, each handed to handle_input.
+pub const INPUT_CHUNK: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    Idle,
+    AwaitingStart,
+    AwaitingTune,
+    AwaitingOpenOk,
+    Open,
+    Closing,
+    Closed,
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Opened,
+    Closed(Option<CloseReason>),
+    Frame(Frame),
+}
+
+pub struct Engine {
+    conf: Conf,
+    state: State,
+    input: BytesMut,
+    output: Vec<u8>,
+    events: VecDeque<Event>,
+    channel_max: u16,
+    frame_max: u32,
+    heartbeat: Option<Duration>,
+    last_recv: Option<Instant>,
+    last_send: Option<Instant>,
+    server_props: Table,
+}
+
+impl Engine {
+    pub fn new(conf: Conf) -> Engine {
+        Engine {
+            conf: conf,
+            state: State::Idle,
+            input: BytesMut::new(),
+            output: Vec::new(),
+            events: VecDeque::new(),
+            channel_max: 0,
+            frame_max: 0,
+            heartbeat: None,
+            last_recv: None,
+            last_send: None,
+            server_props: Table::new(),
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state == State::Open
+    }
+
+    pub fn channel_max(&self) -> u16 {
+        self.channel_max
+    }
+
+    pub fn frame_max(&self) -> u32 {
+        self.frame_max
+    }
+
+    pub fn heartbeat(&self) -> Option<Duration> {
+        self.heartbeat
+    }
+
+    pub fn server_props(&self) -> &Table {
+        &self.server_props
+    }
+
+    pub fn start(&mut self, now: Instant) {
+        self.output.extend_from_slice(&protocol::PROT_HEADER);
+        self.state = State::AwaitingStart;
+        self.last_recv = Some(now);
+    }
+
+    pub fn close(&mut self) -> AmqpResult<()> {
+        if self.state != State::Open {
+            return Ok(());
+        }
+
+        let mut close = connection::Close::default();
+        close.reply_code = protocol::REPLY_SUCCESS as u16;
+        close.reply_text = "OK".to_string();
+        try!(self.send_method(0, &close));
+        self.state = State::Closing;
+        Ok(())
+    }
+
+    // A frame larger than negotiated is refused as soon as its header arrives, before any of
+    // its payload is buffered.
+    pub fn handle_input(&mut self, data: &[u8], now: Instant) -> AmqpResult<()> {
+        if self.state == State::Closed {
+            return Ok(());
+        }
+
+        // Frames are split off the input, so their payloads share its memory.
+        self.input.extend_from_slice(data);
+        let result = loop {
+            if let Some(size) = frame::payload_size(&self.input) {
+                let max = self.max_payload();
+                if size > max {
+                    break Err(AmqpError::FrameSizeErr(size, max));
+                }
+            }
+            match Frame::split_from(&mut self.input) {
+                Ok(Some(frame)) => {
+                    if let Err(err) = self.handle_frame(frame, now) {
+                        break Err(err);
+                    }
+                },
+                Ok(None) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+        if let Err(AmqpError::FrameSizeErr(..)) = result {
+            self.frame_error();
+        }
+        result
+    }
+
+    fn handle_frame(&mut self, frame: Frame, now: Instant) -> AmqpResult<()> {
+        self.last_recv = Some(now);
+        if frame.ty == protocol::FRAME_HEARTBEAT {
+            return Ok(());
+        }
+        if frame.channel != 0 {
+            if self.state == State::Open || self.state == State::Closing {
+                self.events.push_back(Event::Frame(frame));
+            }
+            return Ok(());
+        }
+        try!(expect_method(&frame));
+
+        match self.state {
+            State::AwaitingStart => {
+                let start: connection::Start = try!(frame.to_method());
+                self.server_props = start.server_properties;
+                try!(self.send_method(0, &start_ok(&self.conf)));
+                self.state = State::AwaitingTune;
+            },
+            State::AwaitingTune => {
+                let tune: connection::Tune = try!(frame.to_method());
+                let tune_ok = tune_ok(&tune);
+                try!(self.send_method(0, &tune_ok));
+                try!(self.send_method(0, &connection::Open::default()));
+                self.channel_max = tune.channel_max;
+                self.frame_max = tune_ok.frame_max;
+                if tune.heartbeat > 0 {
+                    self.heartbeat = Some(Duration::from_secs(tune.heartbeat as u64));
+                }
+                self.state = State::AwaitingOpenOk;
+            },
+            State::AwaitingOpenOk => {
+                let _: connection::OpenOk = try!(frame.to_method());
+                self.state = State::Open;
+                self.events.push_back(Event::Opened);
+            },
+            State::Open | State::Closing => match try!(frame.method_id()) {
+                CONNECTION_CLOSE => {
+                    let close: connection::Close = try!(frame.to_method());
+                    try!(self.send_method(0, &connection::CloseOk::default()));
+                    self.closed(Some(CloseReason {
+                        reply_code: close.reply_code,
+                        reply_text: close.reply_text,
+                        class_id: close.class_id,
+                        method_id: close.method_id,
+                    }));
+                },
+                CONNECTION_CLOSE_OK if self.state == State::Closing => self.closed(None),
+                _ => (),
+            },
+            State::Idle | State::Closed => (),
+        }
+        Ok(())
+    }
+
+    pub fn send_frame(&mut self, frame: &Frame) {
+        frame.encode_into(&mut self.output);
+    }
+
+    pub fn send_method<M: Method>(&mut self, channel: u16, method: &M) -> AmqpResult<()> {
+        let frame = try!(Frame::from_method(channel, method));
+        self.send_frame(&frame);
+        Ok(())
+    }
+
+    pub fn poll_transmit(&mut self, now: Instant) -> Option<Vec<u8>> {
+        if self.output.is_empty() {
+            return None;
+        }
+        self.last_send = Some(now);
+        Some(mem::replace(&mut self.output, Vec::new()))
+    }
+
+    // A driver that writes frames itself, bypassing poll_transmit, reports the traffic here,
+    // so no heartbeat is sent while it keeps flowing.
+    pub fn on_transmit(&mut self, now: Instant) {
+        self.last_send = Some(now);
+    }
+
+    pub fn poll_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        let heartbeat = match self.heartbeat {
+            Some(heartbeat) if self.state == State::Open || self.state == State::Closing => {
+                heartbeat
+            },
+            _ => return None,
+        };
+
+        let send = self.last_send.map(|t| t + heartbeat);
+        let recv = self.last_recv.map(|t| t + heartbeat * 2);
+        match (send, recv) {
+            (Some(send), Some(recv)) => Some(cmp::min(send, recv)),
+            (send, recv) => send.or(recv),
+        }
+    }
+
+    // The broker is considered gone after two heartbeat intervals without any frame from it.
+    pub fn handle_timeout(&mut self, now: Instant) -> AmqpResult<()> {
+        let heartbeat = match self.heartbeat {
+            Some(heartbeat) if self.state == State::Open || self.state == State::Closing => {
+                heartbeat
+            },
+            _ => return Ok(()),
+        };
+
+        if self.last_recv.map_or(false, |t| now >= t + heartbeat * 2) {
+            self.closed(None);
+            return Err(AmqpError::Timeout);
+        }
+        if self.output.is_empty() && self.last_send.map_or(true, |t| now >= t + heartbeat) {
+            self.send_frame(&Frame::heartbeat());
+        }
+        Ok(())
+    }
+
+    // Until tune the broker may only send frames of the minimum size.
+    fn max_payload(&self) -> usize {
+        if self.frame_max == 0 {
+            frame::body_frame_max(protocol::FRAME_MIN_SIZE as u32)
+        } else {
+            frame::body_frame_max(self.frame_max)
+        }
+    }
+
+    // The broker is told with FRAME_ERROR and nothing it sends is read any more.
+    fn frame_error(&mut self) {
+        let mut close = connection::Close::default();
+        close.reply_code = protocol::FRAME_ERROR;
+        close.reply_text = "FRAME_ERROR - frame larger than frame_max".to_string();
+        let _ = self.send_method(0, &close);
+        self.input = BytesMut::new();
+        self.closed(None);
+    }
+
+    fn closed(&mut self, reason: Option<CloseReason>) {
+        self.state = State::Closed;
+        self.events.push_back(Event::Closed(reason));
+    }
+}
+
+fn expect_method(frame: &Frame) -> AmqpResult<()> {
+    if frame.ty != protocol::FRAME_METHOD {
+        Err(AmqpError::FrameTypeErr(frame.ty, protocol::FRAME_METHOD))
+    } else {
+        Ok(())
+    }
+}
+
+fn client_props() -> Table {
+    let mut client_props = Table::new();
+    client_props.insert("product".to_string(), LongString(b"rust-amqp".to_vec()));
+    client_props.insert("platform".to_string(), LongString(b"rust".to_vec()));
+    client_props.insert("version".to_string(), LongString(b"0.0.1".to_vec()));
+    client_props.insert("information".to_string(),
+                        LongString(b"https://github.com/zbaitu/rust-amqp-client".to_vec()));
+
+    let mut caps = Table::new();
+    caps.insert("publisher_confirms".to_string(), Bool(true));
+    caps.insert("exchange_exchange_bindings".to_string(), Bool(true));
+    caps.insert("basic.nack".to_string(), Bool(true));
+    caps.insert("consumer_cancel_notify".to_string(), Bool(true));
+    caps.insert("connection.blocked".to_string(), Bool(true));
+    caps.insert("authentication_failure_close".to_string(), Bool(true));
+    client_props.insert("capabilities".to_string(), FieldTable(caps));
+
+    client_props
+}
+
+fn start_ok(conf: &Conf) -> connection::StartOk {
+    let mut start_ok = connection::StartOk::default();
+    start_ok.client_properties = client_props();
+    start_ok.mechanism = "PLAIN".to_string();
+    start_ok.response = format!("\0{}\0{}", conf.user, conf.password).into_bytes();
+    start_ok.locale = "en_US".to_string();
+    start_ok
+}
+
+fn tune_ok(tune: &connection::Tune) -> connection::TuneOk {
+    let mut tune_ok = connection::TuneOk::default();
+    tune_ok.channel_max = tune.channel_max;
+    tune_ok.frame_max = if tune.frame_max == 0 { DEFAULT_FRAME_MAX } else { tune.frame_max };
+    tune_ok.heartbeat = tune.heartbeat;
+    tune_ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::channel;
+
+    fn server_bytes<M: Method>(channel: u16, method: &M) -> Vec<u8> {
+        let mut buf = Vec::new();
+        Frame::from_method(channel, method).unwrap().encode_into(&mut buf);
+        buf
+    }
+
+    fn client_frames(engine: &mut Engine, now: Instant) -> Vec<Frame> {
+        let data = engine.poll_transmit(now).unwrap_or_else(Vec::new);
+        let mut data = &data[..];
+        if data.starts_with(&protocol::PROT_HEADER) {
+            data = &data[protocol::PROT_HEADER.len()..];
+        }
+
+        let mut frames = Vec::new();
+        while let Some((frame, n)) = Frame::decode(data).unwrap() {
+            frames.push(frame);
+            data = &data[n..];
+        }
+        frames
+    }
+
+    fn open(now: Instant, heartbeat: u16) -> Engine {
+        let mut engine = Engine::new(Conf::default());
+        engine.start(now);
+        assert_eq!(&engine.poll_transmit(now).unwrap()[..], &protocol::PROT_HEADER[..]);
+
+        engine.handle_input(&server_bytes(0, &connection::Start::default()), now).unwrap();
+        let frames = client_frames(&mut engine, now);
+        let start_ok: connection::StartOk = frames[0].to_method().unwrap();
+        assert_eq!(start_ok.response, b"\0guest\0guest".to_vec());
+
+        let mut tune = connection::Tune::default();
+        tune.channel_max = 2047;
+        tune.frame_max = 131072;
+        tune.heartbeat = heartbeat;
+        // Tune and OpenOk arrive in one read, the second split across two.
+        let mut data = server_bytes(0, &tune);
+        data.extend(server_bytes(0, &connection::OpenOk::default()));
+        let (a, b) = data.split_at(data.len() - 3);
+        engine.handle_input(a, now).unwrap();
+        assert_eq!(engine.state(), State::AwaitingOpenOk);
+        engine.handle_input(b, now).unwrap();
+
+        let frames = client_frames(&mut engine, now);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].method_id().unwrap(), (10, 40));
+        match engine.poll_event() {
+            Some(Event::Opened) => (),
+            other => panic!("{:?}", other),
+        }
+        engine
+    }
+
+    #[test]
+    fn test_handshake() {
+        let now = Instant::now();
+        let mut engine = open(now, 0);
+        assert!(engine.is_open());
+        assert_eq!(engine.channel_max(), 2047);
+        assert_eq!(engine.frame_max(), 131072);
+        assert_eq!(engine.poll_timeout(), None);
+
+        engine.handle_input(&server_bytes(1, &channel::OpenOk::default()), now).unwrap();
+        match engine.poll_event() {
+            Some(Event::Frame(ref frame)) => assert_eq!(frame.channel, 1),
+            other => panic!("{:?}", other),
+        }
+
+        engine.close().unwrap();
+        assert_eq!(client_frames(&mut engine, now)[0].method_id().unwrap(), CONNECTION_CLOSE);
+        engine.handle_input(&server_bytes(0, &connection::CloseOk::default()), now).unwrap();
+        match engine.poll_event() {
+            Some(Event::Closed(None)) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_server_close() {
+        let now = Instant::now();
+        let mut engine = open(now, 0);
+
+        let mut close = connection::Close::default();
+        close.reply_code = 320;
+        close.reply_text = "CONNECTION_FORCED".to_string();
+        engine.handle_input(&server_bytes(0, &close), now).unwrap();
+
+        assert_eq!(client_frames(&mut engine, now)[0].method_id().unwrap(), CONNECTION_CLOSE_OK);
+        match engine.poll_event() {
+            Some(Event::Closed(Some(ref reason))) => assert_eq!(reason.reply_code, 320),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(engine.state(), State::Closed);
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let now = Instant::now();
+        let mut engine = open(now, 10);
+        assert_eq!(engine.poll_timeout(), Some(now + Duration::from_secs(10)));
+
+        let later = now + Duration::from_secs(10);
+        engine.handle_timeout(later).unwrap();
+        let frames = client_frames(&mut engine, later);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].ty, protocol::FRAME_HEARTBEAT);
+        assert_eq!(engine.poll_timeout(), Some(now + Duration::from_secs(20)));
+
+        match engine.handle_timeout(now + Duration::from_secs(20)) {
+            Err(AmqpError::Timeout) => (),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(engine.state(), State::Closed);
+    }
+
+    #[test]
+    fn test_frame_too_large() {
+        let now = Instant::now();
+        let mut engine = open(now, 0);
+
+        // Only the header has arrived, announcing one byte more than frame_max allows.
+        let data = [protocol::FRAME_BODY, 0, 1, 0, 0x01, 0xff, 0xf9];
+        match engine.handle_input(&data, now) {
+            Err(AmqpError::FrameSizeErr(131065, 131064)) => (),
+            other => panic!("{:?}", other),
+        }
+        let close: connection::Close = client_frames(&mut engine, now)[0].to_method().unwrap();
+        assert_eq!(close.reply_code, protocol::FRAME_ERROR);
+        assert_eq!(engine.state(), State::Closed);
+        match engine.poll_event() {
+            Some(Event::Closed(None)) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unlimited_frame_max() {
+        let now = Instant::now();
+        let mut engine = Engine::new(Conf::default());
+        engine.start(now);
+        let data = [protocol::FRAME_METHOD, 0, 0, 0, 0, 0x13, 0x88];
+        match engine.handle_input(&data, now) {
+            Err(AmqpError::FrameSizeErr(5000, 4088)) => (),
+            other => panic!("{:?}", other),
+        }
+
+        let mut engine = Engine::new(Conf::default());
+        engine.start(now);
+        engine.poll_transmit(now);
+        engine.handle_input(&server_bytes(0, &connection::Start::default()), now).unwrap();
+        engine.handle_input(&server_bytes(0, &connection::Tune::default()), now).unwrap();
+        let tune_ok: connection::TuneOk = client_frames(&mut engine, now)[1].to_method().unwrap();
+        assert_eq!(tune_ok.frame_max, DEFAULT_FRAME_MAX);
+        assert_eq!(engine.frame_max(), DEFAULT_FRAME_MAX);
+    }
+}
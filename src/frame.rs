@@ -1,12 +1,26 @@
-use method::{self, Method};
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
+use bytes::{Bytes, BytesMut};
+
+use std::cmp;
+
+use content::ContentHeader;
+use method::{self, Method, MethodRef};
 use protocol::*;
 use result::*;
+use types::*;
+
+pub const FRAME_HEADER_SIZE: usize = 7;
+pub const FRAME_OVERHEAD: usize = FRAME_HEADER_SIZE + 1;
+
+// Body chunk size when frame_max is unlimited, so a large body still goes out, and is
+// buffered, in bounded pieces.
+const UNLIMITED_BODY_CHUNK: usize = 128 * 1024;
 
 #[derive(Debug)]
 pub struct Frame {
     pub ty: u8,
     pub channel: u16,
-    pub payload: Vec<u8>,
+    pub payload: Bytes,
 }
 
 impl Frame {
@@ -15,14 +29,195 @@ impl Frame {
         let frame = Frame {
             ty: FRAME_METHOD,
             channel: channel,
-            payload: payload,
+            payload: Bytes::from(payload),
         };
         Ok(frame)
     }
 
     pub fn to_method<M: Method>(&self) -> AmqpResult<M> {
+        try!(self.check_method::<M>());
         method::de(&self.payload)
     }
+
+    // Decodes a view whose strings borrow from the payload instead of being copied.
+    pub fn method_ref<'a, M: MethodRef<'a>>(&'a self) -> AmqpResult<M> {
+        try!(self.check_method::<M::Owned>());
+        M::decode(&self.payload)
+    }
+
+    fn check_method<M: Method>(&self) -> AmqpResult<()> {
+        if self.ty != FRAME_METHOD {
+            return Err(AmqpError::FrameTypeErr(self.ty, FRAME_METHOD));
+        }
+
+        let expected = try!(try!(Frame::from_method(self.channel, &M::default())).method_id());
+        let got = try!(self.method_id());
+        if got != expected {
+            return Err(AmqpError::UnexpectedFrame {
+                expected: expected,
+                got: got,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn method(&self) -> AmqpResult<AmqpMethod> {
+        AmqpMethod::decode(&self.payload)
+    }
+
+    pub fn method_id(&self) -> AmqpResult<(Short, Short)> {
+        let mut payload = &self.payload[..];
+        let class_id = try!(payload.read_u16::<BigEndian>());
+        let method_id = try!(payload.read_u16::<BigEndian>());
+        Ok((class_id, method_id))
+    }
+
+    pub fn from_content_header(channel: u16, header: &ContentHeader) -> AmqpResult<Frame> {
+        let payload = try!(header.se());
+        let frame = Frame {
+            ty: FRAME_HEADER,
+            channel: channel,
+            payload: Bytes::from(payload),
+        };
+        Ok(frame)
+    }
+
+    pub fn to_content_header(&self) -> AmqpResult<ContentHeader> {
+        ContentHeader::de(&self.payload)
+    }
+
+    pub fn from_body<B: Into<Bytes>>(channel: u16, body: B) -> Frame {
+        Frame {
+            ty: FRAME_BODY,
+            channel: channel,
+            payload: body.into(),
+        }
+    }
+
+    pub fn heartbeat() -> Frame {
+        Frame {
+            ty: FRAME_HEARTBEAT,
+            channel: 0,
+            payload: Bytes::new(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        FRAME_HEADER_SIZE + self.payload.len() + 1
+    }
+
+    pub fn encode_into(&self, buf: &mut Vec<u8>) {
+        encode_frame_into(buf, self.ty, self.channel, &self.payload);
+    }
+
+    // Returns the frame and the number of bytes it took, or None while buf holds only part of it.
+    pub fn decode(buf: &[u8]) -> AmqpResult<Option<(Frame, usize)>> {
+        let end = match try!(payload_end(buf)) {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+
+        let frame = Frame {
+            ty: buf[0],
+            channel: BigEndian::read_u16(&buf[1..3]),
+            payload: Bytes::from(buf[FRAME_HEADER_SIZE..end].to_vec()),
+        };
+        Ok(Some((frame, end + 1)))
+    }
+
+    // Like decode, but splits the frame off buf so the payload shares its memory.
+    pub fn split_from(buf: &mut BytesMut) -> AmqpResult<Option<Frame>> {
+        let end = match try!(payload_end(buf)) {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+
+        let mut data = buf.split_to(end + 1);
+        data.truncate(end);
+        let payload = data.split_off(FRAME_HEADER_SIZE).freeze();
+        let frame = Frame {
+            ty: data[0],
+            channel: BigEndian::read_u16(&data[1..3]),
+            payload: payload,
+        };
+        Ok(Some(frame))
+    }
+}
+
+pub fn encode_frame_into(buf: &mut Vec<u8>, ty: u8, channel: u16, payload: &[u8]) {
+    buf.reserve(FRAME_HEADER_SIZE + payload.len() + 1);
+    buf.write_u8(ty).unwrap();
+    buf.write_u16::<BigEndian>(channel).unwrap();
+    buf.write_u32::<BigEndian>(payload.len() as u32).unwrap();
+    buf.extend_from_slice(payload);
+    buf.write_u8(FRAME_END).unwrap();
+}
+
+// Encodes the method straight after its frame header, leaving buf as it was on error.
+pub fn encode_method_into<M: Method>(buf: &mut Vec<u8>, channel: u16, method: &M)
+                                     -> AmqpResult<()> {
+    let start = buf.len();
+    buf.write_u8(FRAME_METHOD).unwrap();
+    buf.write_u16::<BigEndian>(channel).unwrap();
+    buf.write_u32::<BigEndian>(0).unwrap();
+    if let Err(err) = method.encode_into(buf) {
+        buf.truncate(start);
+        return Err(err);
+    }
+    let size = buf.len() - start - FRAME_HEADER_SIZE;
+    BigEndian::write_u32(&mut buf[start + 3..start + FRAME_HEADER_SIZE], size as u32);
+    buf.write_u8(FRAME_END).unwrap();
+    Ok(())
+}
+
+// The largest body chunk a frame can carry; a peer that tuned frame_max below the frame
+// overhead still gets one byte per frame rather than an underflow.
+pub fn body_frame_max(frame_max: u32) -> usize {
+    if frame_max == 0 {
+        UNLIMITED_BODY_CHUNK
+    } else {
+        cmp::max(frame_max as usize, FRAME_OVERHEAD + 1) - FRAME_OVERHEAD
+    }
+}
+
+// The payload size announced by the frame header at the start of buf, once it is all there.
+pub fn payload_size(buf: &[u8]) -> Option<usize> {
+    if buf.len() < FRAME_HEADER_SIZE {
+        None
+    } else {
+        Some(BigEndian::read_u32(&buf[3..7]) as usize)
+    }
+}
+
+fn payload_end(buf: &[u8]) -> AmqpResult<Option<usize>> {
+    let size = match payload_size(buf) {
+        Some(size) => size,
+        None => return Ok(None),
+    };
+    let end = FRAME_HEADER_SIZE + size;
+    if buf.len() <= end {
+        return Ok(None);
+    }
+    if buf[end] != FRAME_END {
+        return Err(AmqpError::FrameEndErr(buf[end]));
+    }
+    Ok(Some(end))
+}
+
+// Joins the payloads of a content body; a body of a single frame is passed on without copying.
+pub fn join_body(mut parts: Vec<Bytes>) -> Bytes {
+    match parts.len() {
+        0 => Bytes::new(),
+        1 => parts.pop().unwrap(),
+        _ => {
+            let size = parts.iter().map(|part| part.len()).sum();
+            let mut body = Vec::with_capacity(size);
+            for part in &parts {
+                body.extend_from_slice(part);
+            }
+            Bytes::from(body)
+        },
+    }
 }
 
 #[cfg(test)]
@@ -43,4 +238,173 @@ mod tests {
 
         assert_eq!(a, b);
     }
+
+    #[test]
+    fn test_method_id() {
+        let flow = channel::Flow::default();
+        let frame = Frame::from_method(1, &flow).unwrap();
+        assert_eq!(frame.method_id().unwrap(), (20, 20));
+    }
+
+    #[test]
+    fn test_unexpected_method() {
+        let frame = Frame::from_method(1, &channel::Flow::default()).unwrap();
+        match frame.to_method::<channel::FlowOk>() {
+            Err(AmqpError::UnexpectedFrame { expected: (20, 21), got: (20, 20) }) => (),
+            other => panic!("{:?}", other),
+        }
+
+        let body = Frame::from_body(1, frame.payload.clone());
+        match body.to_method::<channel::Flow>() {
+            Err(AmqpError::FrameTypeErr(FRAME_BODY, FRAME_METHOD)) => (),
+            other => panic!("{:?}", other),
+        }
+
+        let mut payload = frame.payload.to_vec();
+        payload.push(0);
+        let trailing = Frame {
+            ty: FRAME_METHOD,
+            channel: 1,
+            payload: payload.into(),
+        };
+        match trailing.to_method::<channel::Flow>() {
+            Err(AmqpError::SyntaxErr(_)) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_amqp_method() {
+        let mut deliver = basic::Deliver::default();
+        deliver.delivery_tag = 7;
+        let frame = Frame::from_method(1, &deliver).unwrap();
+        let method = frame.method().unwrap();
+        assert_eq!(method.class_id(), 60);
+        assert_eq!(method.method_id(), 60);
+        assert_eq!(method.name(), "basic.deliver");
+        assert_eq!(method, AmqpMethod::BasicDeliver(deliver));
+
+        let frame = Frame::from_method(0, &connection::TuneOk::default()).unwrap();
+        assert_eq!(frame.method().unwrap().name(), "connection.tune-ok");
+
+        match AmqpMethod::decode(&[0, 60, 0, 1]) {
+            Err(AmqpError::UnknownMethod(60, 1)) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_method_ref() {
+        let mut deliver = basic::Deliver::default();
+        deliver.consumer_tag = "ctag".to_string();
+        deliver.delivery_tag = 7;
+        deliver.redelivered = true;
+        deliver.routing_key = "key".to_string();
+        let frame = Frame::from_method(1, &deliver).unwrap();
+
+        let view: basic::DeliverRef = frame.method_ref().unwrap();
+        assert_eq!(view.consumer_tag, "ctag");
+        assert_eq!(view.delivery_tag, 7);
+        assert!(view.redelivered);
+        assert_eq!(view.exchange, "");
+        let range = frame.payload.as_ptr() as usize..frame.payload.as_ptr() as usize + frame.payload.len();
+        assert!(range.contains(&(view.routing_key.as_ptr() as usize)));
+        assert_eq!(view.into_owned(), deliver);
+
+        match frame.method_ref::<basic::ReturnRef>() {
+            Err(AmqpError::UnexpectedFrame { expected: (60, 50), got: (60, 60) }) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_split_from() {
+        let mut buf = Vec::new();
+        Frame::from_body(2, b"hello".to_vec()).encode_into(&mut buf);
+        Frame::heartbeat().encode_into(&mut buf);
+        let mut buf = BytesMut::from(&buf[..]);
+
+        let frame = Frame::split_from(&mut buf).unwrap().unwrap();
+        assert_eq!(frame.ty, FRAME_BODY);
+        assert_eq!(frame.channel, 2);
+        assert_eq!(frame.payload, &b"hello"[..]);
+        assert_eq!(buf.len(), Frame::heartbeat().size());
+
+        buf.truncate(3);
+        assert!(Frame::split_from(&mut buf).unwrap().is_none());
+        assert_eq!(buf.len(), 3);
+    }
+
+    #[test]
+    fn test_encode_method_into() {
+        let mut publish = basic::Publish::default();
+        publish.routing_key = "key".to_string();
+        let mut buf = vec![1, 2, 3];
+        encode_method_into(&mut buf, 5, &publish).unwrap();
+
+        let mut expected = vec![1, 2, 3];
+        Frame::from_method(5, &publish).unwrap().encode_into(&mut expected);
+        assert_eq!(buf, expected);
+
+        publish.routing_key = "k".repeat(256);
+        assert!(encode_method_into(&mut buf, 5, &publish).is_err());
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn test_join_body() {
+        assert!(join_body(Vec::new()).is_empty());
+
+        let part = Bytes::from(b"hello".to_vec());
+        let joined = join_body(vec![part.clone()]);
+        assert_eq!(joined.as_ptr(), part.as_ptr());
+
+        let joined = join_body(vec![part, Bytes::from_static(b" world")]);
+        assert_eq!(joined, &b"hello world"[..]);
+    }
+
+    #[test]
+    fn test_body_frame_max() {
+        assert_eq!(body_frame_max(4096), 4088);
+        assert_eq!(body_frame_max(4), 1);
+        assert_eq!(body_frame_max(0), UNLIMITED_BODY_CHUNK);
+    }
+
+    // Inputs that used to panic or read out of bounds in the method decoder.
+    #[test]
+    fn test_malformed() {
+        let inputs: &[&[u8]] = &[
+            &[0, 10, 0, 10, 0, 9, 0, 0, 0, 4, 1, b'k', b'x', 0],
+            &[0, 10, 0, 10, 0, 9, 0xff, 0xff, 0xff, 0xff],
+            &[0, 10, 0, 10, 0, 9, 0, 0, 0, 7, 1, b'k', b'A', 0xff, 0xff, 0xff, 0xff],
+            &[0, 10, 0, 10, 0, 9, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff],
+            &[0, 60, 0, 60, 5, b'a'],
+            &[0, 60],
+        ];
+        for input in inputs {
+            assert!(AmqpMethod::decode(input).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let frame = Frame::from_method(3, &channel::Flow::default()).unwrap();
+        let mut buf = Vec::new();
+        frame.encode_into(&mut buf);
+        assert_eq!(buf.len(), frame.size());
+
+        assert!(Frame::decode(&buf[..buf.len() - 1]).unwrap().is_none());
+        buf.push(FRAME_HEARTBEAT);
+        let (decoded, n) = Frame::decode(&buf).unwrap().unwrap();
+        assert_eq!(n, frame.size());
+        assert_eq!(decoded.channel, 3);
+        assert_eq!(decoded.payload, frame.payload);
+
+        let end = buf.len() - 2;
+        buf[end] = 0;
+        match Frame::decode(&buf) {
+            Err(AmqpError::FrameEndErr(0)) => (),
+            other => panic!("{:?}", other),
+        }
+    }
 }
@@ -1,15 +1,48 @@
 extern crate byteorder;
-extern crate rustc_serialize;
-extern crate rustc_serialize as sede;
+extern crate bytes;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "msgpack")]
+extern crate rmp_serde;
 #[macro_use]
 extern crate zbase;
+#[cfg(any(feature = "gzip", feature = "deflate"))]
+extern crate flate2;
+#[cfg(feature = "zstd")]
+extern crate zstd;
+#[cfg(feature = "tokio")]
+extern crate futures;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "tokio")]
+extern crate tokio_util;
 
+#[macro_use]
+pub mod types;
+
+pub mod arguments;
+#[cfg(feature = "tokio")]
+pub mod async_client;
+pub mod channel;
+pub mod codec;
+pub mod compression;
 pub mod conf;
 pub mod connection;
+pub mod consumer;
+pub mod content;
+pub mod death;
+pub mod engine;
 pub mod frame;
 pub mod method;
 pub mod protocol;
 pub mod result;
+pub mod retry;
+pub mod rpc;
+#[cfg(feature = "serde")]
+pub mod serde_types;
 pub mod session;
+pub mod socket;
+pub mod stream;
 pub mod transport;
-pub mod types;
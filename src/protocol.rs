@@ -1,3 +1,9 @@
+use byteorder::{BigEndian, ReadBytesExt};
+
+use method;
+use result::*;
+use types::Short;
+
 #[derive(Debug)]
 pub struct Header {
     name: &'static [u8; 4],
@@ -43,11 +49,77 @@ pub const NOT_ALLOWED: u16 = 530;
 pub const NOT_IMPLEMENTED: u16 = 540;
 pub const INTERNAL_ERROR: u16 = 541;
 
+pub const CONNECTION_START: (Short, Short) = (10, 10);
+pub const CONNECTION_START_OK: (Short, Short) = (10, 11);
+pub const CONNECTION_SECURE: (Short, Short) = (10, 20);
+pub const CONNECTION_SECURE_OK: (Short, Short) = (10, 21);
+pub const CONNECTION_TUNE: (Short, Short) = (10, 30);
+pub const CONNECTION_TUNE_OK: (Short, Short) = (10, 31);
+pub const CONNECTION_OPEN: (Short, Short) = (10, 40);
+pub const CONNECTION_OPEN_OK: (Short, Short) = (10, 41);
+pub const CONNECTION_CLOSE: (Short, Short) = (10, 50);
+pub const CONNECTION_CLOSE_OK: (Short, Short) = (10, 51);
+pub const CONNECTION_BLOCKED: (Short, Short) = (10, 60);
+pub const CONNECTION_UNBLOCKED: (Short, Short) = (10, 61);
+pub const CHANNEL_OPEN: (Short, Short) = (20, 10);
+pub const CHANNEL_OPEN_OK: (Short, Short) = (20, 11);
+pub const CHANNEL_FLOW: (Short, Short) = (20, 20);
+pub const CHANNEL_FLOW_OK: (Short, Short) = (20, 21);
+pub const CHANNEL_CLOSE: (Short, Short) = (20, 40);
+pub const CHANNEL_CLOSE_OK: (Short, Short) = (20, 41);
+pub const ACCESS_REQUEST: (Short, Short) = (30, 10);
+pub const ACCESS_REQUEST_OK: (Short, Short) = (30, 11);
+pub const EXCHANGE_DECLARE: (Short, Short) = (40, 10);
+pub const EXCHANGE_DECLARE_OK: (Short, Short) = (40, 11);
+pub const EXCHANGE_DELETE: (Short, Short) = (40, 20);
+pub const EXCHANGE_DELETE_OK: (Short, Short) = (40, 21);
+pub const EXCHANGE_BIND: (Short, Short) = (40, 30);
+pub const EXCHANGE_BIND_OK: (Short, Short) = (40, 31);
+pub const EXCHANGE_UNBIND: (Short, Short) = (40, 40);
+pub const EXCHANGE_UNBIND_OK: (Short, Short) = (40, 51);
+pub const QUEUE_DECLARE: (Short, Short) = (50, 10);
+pub const QUEUE_DECLARE_OK: (Short, Short) = (50, 11);
+pub const QUEUE_BIND: (Short, Short) = (50, 20);
+pub const QUEUE_BIND_OK: (Short, Short) = (50, 21);
+pub const QUEUE_PURGE: (Short, Short) = (50, 30);
+pub const QUEUE_PURGE_OK: (Short, Short) = (50, 31);
+pub const QUEUE_DELETE: (Short, Short) = (50, 40);
+pub const QUEUE_DELETE_OK: (Short, Short) = (50, 41);
+pub const QUEUE_UNBIND: (Short, Short) = (50, 50);
+pub const QUEUE_UNBIND_OK: (Short, Short) = (50, 51);
+pub const BASIC_QOS: (Short, Short) = (60, 10);
+pub const BASIC_QOS_OK: (Short, Short) = (60, 11);
+pub const BASIC_CONSUME: (Short, Short) = (60, 20);
+pub const BASIC_CONSUME_OK: (Short, Short) = (60, 21);
+pub const BASIC_CANCEL: (Short, Short) = (60, 30);
+pub const BASIC_CANCEL_OK: (Short, Short) = (60, 31);
+pub const BASIC_PUBLISH: (Short, Short) = (60, 40);
+pub const BASIC_RETURN: (Short, Short) = (60, 50);
+pub const BASIC_DELIVER: (Short, Short) = (60, 60);
+pub const BASIC_GET: (Short, Short) = (60, 70);
+pub const BASIC_GET_OK: (Short, Short) = (60, 71);
+pub const BASIC_GET_EMPTY: (Short, Short) = (60, 72);
+pub const BASIC_ACK: (Short, Short) = (60, 80);
+pub const BASIC_REJECT: (Short, Short) = (60, 90);
+pub const BASIC_RECOVER_ASYNC: (Short, Short) = (60, 100);
+pub const BASIC_RECOVER: (Short, Short) = (60, 110);
+pub const BASIC_RECOVER_OK: (Short, Short) = (60, 111);
+pub const BASIC_NACK: (Short, Short) = (60, 120);
+pub const TX_SELECT: (Short, Short) = (90, 10);
+pub const TX_SELECT_OK: (Short, Short) = (90, 11);
+pub const TX_COMMIT: (Short, Short) = (90, 20);
+pub const TX_COMMIT_OK: (Short, Short) = (90, 21);
+pub const TX_ROLLBACK: (Short, Short) = (90, 30);
+pub const TX_ROLLBACK_OK: (Short, Short) = (90, 31);
+pub const CONFIRM_SELECT: (Short, Short) = (85, 10);
+pub const CONFIRM_SELECT_OK: (Short, Short) = (85, 11);
+
 pub mod connection {
-    use method::Method;
+    use method::{Method, MethodRef, Reader, Writer};
+    use result::*;
     use types::*;
     
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Start {
         cid: Short,
         id: Short,
@@ -59,12 +131,37 @@ pub mod connection {
     }
 
     impl Method for Start {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "mechanisms" => Some(StrType::Long),
-                "locales" => Some(StrType::Long),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_octet(self.version_major);
+            writer.write_octet(self.version_minor);
+            try!(writer.write_table(&self.server_properties));
+            writer.write_long_str(&self.mechanisms);
+            writer.write_long_str(&self.locales);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Start> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let version_major = try!(reader.read_octet("version_major"));
+            let version_minor = try!(reader.read_octet("version_minor"));
+            let server_properties = try!(reader.read_table("server_properties"));
+            let mechanisms = try!(reader.read_long_str("mechanisms"));
+            let locales = try!(reader.read_long_str("locales"));
+            try!(reader.finish());
+            Ok(Start {
+                cid: cid,
+                id: id,
+                version_major: version_major,
+                version_minor: version_minor,
+                server_properties: server_properties,
+                mechanisms: mechanisms,
+                locales: locales,
+            })
         }
     }
 
@@ -82,7 +179,55 @@ pub mod connection {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct StartRef<'a> {
+        cid: Short,
+        id: Short,
+        pub version_major: Octet,
+        pub version_minor: Octet,
+        pub server_properties: Table,
+        pub mechanisms: &'a [u8],
+        pub locales: &'a [u8],
+    }
+
+    impl<'a> MethodRef<'a> for StartRef<'a> {
+        type Owned = Start;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<StartRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let version_major = try!(reader.read_octet("version_major"));
+            let version_minor = try!(reader.read_octet("version_minor"));
+            let server_properties = try!(reader.read_table("server_properties"));
+            let mechanisms = try!(reader.read_long_str_ref("mechanisms"));
+            let locales = try!(reader.read_long_str_ref("locales"));
+            try!(reader.finish());
+            Ok(StartRef {
+                cid: cid,
+                id: id,
+                version_major: version_major,
+                version_minor: version_minor,
+                server_properties: server_properties,
+                mechanisms: mechanisms,
+                locales: locales,
+            })
+        }
+
+        fn into_owned(self) -> Start {
+            Start {
+                cid: self.cid,
+                id: self.id,
+                version_major: self.version_major,
+                version_minor: self.version_minor,
+                server_properties: self.server_properties,
+                mechanisms: self.mechanisms.to_vec(),
+                locales: self.locales.to_vec(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct StartOk {
         cid: Short,
         id: Short,
@@ -93,13 +238,34 @@ pub mod connection {
     }
 
     impl Method for StartOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "mechanism" => Some(StrType::Short),
-                "response" => Some(StrType::Long),
-                "locale" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            try!(writer.write_table(&self.client_properties));
+            try!(writer.write_short_str(&self.mechanism));
+            writer.write_long_str(&self.response);
+            try!(writer.write_short_str(&self.locale));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<StartOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let client_properties = try!(reader.read_table("client_properties"));
+            let mechanism = try!(reader.read_short_str("mechanism"));
+            let response = try!(reader.read_long_str("response"));
+            let locale = try!(reader.read_short_str("locale"));
+            try!(reader.finish());
+            Ok(StartOk {
+                cid: cid,
+                id: id,
+                client_properties: client_properties,
+                mechanism: mechanism,
+                response: response,
+                locale: locale,
+            })
         }
     }
 
@@ -116,7 +282,51 @@ pub mod connection {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct StartOkRef<'a> {
+        cid: Short,
+        id: Short,
+        pub client_properties: Table,
+        pub mechanism: &'a str,
+        pub response: &'a [u8],
+        pub locale: &'a str,
+    }
+
+    impl<'a> MethodRef<'a> for StartOkRef<'a> {
+        type Owned = StartOk;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<StartOkRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let client_properties = try!(reader.read_table("client_properties"));
+            let mechanism = try!(reader.read_short_str_ref("mechanism"));
+            let response = try!(reader.read_long_str_ref("response"));
+            let locale = try!(reader.read_short_str_ref("locale"));
+            try!(reader.finish());
+            Ok(StartOkRef {
+                cid: cid,
+                id: id,
+                client_properties: client_properties,
+                mechanism: mechanism,
+                response: response,
+                locale: locale,
+            })
+        }
+
+        fn into_owned(self) -> StartOk {
+            StartOk {
+                cid: self.cid,
+                id: self.id,
+                client_properties: self.client_properties,
+                mechanism: self.mechanism.to_string(),
+                response: self.response.to_vec(),
+                locale: self.locale.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct Secure {
         cid: Short,
         id: Short,
@@ -124,11 +334,25 @@ pub mod connection {
     }
 
     impl Method for Secure {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "challenge" => Some(StrType::Long),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_long_str(&self.challenge);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Secure> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let challenge = try!(reader.read_long_str("challenge"));
+            try!(reader.finish());
+            Ok(Secure {
+                cid: cid,
+                id: id,
+                challenge: challenge,
+            })
         }
     }
 
@@ -142,7 +366,39 @@ pub mod connection {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct SecureRef<'a> {
+        cid: Short,
+        id: Short,
+        pub challenge: &'a [u8],
+    }
+
+    impl<'a> MethodRef<'a> for SecureRef<'a> {
+        type Owned = Secure;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<SecureRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let challenge = try!(reader.read_long_str_ref("challenge"));
+            try!(reader.finish());
+            Ok(SecureRef {
+                cid: cid,
+                id: id,
+                challenge: challenge,
+            })
+        }
+
+        fn into_owned(self) -> Secure {
+            Secure {
+                cid: self.cid,
+                id: self.id,
+                challenge: self.challenge.to_vec(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct SecureOk {
         cid: Short,
         id: Short,
@@ -150,11 +406,25 @@ pub mod connection {
     }
 
     impl Method for SecureOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "response" => Some(StrType::Long),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_long_str(&self.response);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<SecureOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let response = try!(reader.read_long_str("response"));
+            try!(reader.finish());
+            Ok(SecureOk {
+                cid: cid,
+                id: id,
+                response: response,
+            })
         }
     }
 
@@ -168,7 +438,39 @@ pub mod connection {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct SecureOkRef<'a> {
+        cid: Short,
+        id: Short,
+        pub response: &'a [u8],
+    }
+
+    impl<'a> MethodRef<'a> for SecureOkRef<'a> {
+        type Owned = SecureOk;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<SecureOkRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let response = try!(reader.read_long_str_ref("response"));
+            try!(reader.finish());
+            Ok(SecureOkRef {
+                cid: cid,
+                id: id,
+                response: response,
+            })
+        }
+
+        fn into_owned(self) -> SecureOk {
+            SecureOk {
+                cid: self.cid,
+                id: self.id,
+                response: self.response.to_vec(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct Tune {
         cid: Short,
         id: Short,
@@ -178,10 +480,31 @@ pub mod connection {
     }
 
     impl Method for Tune {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.channel_max);
+            writer.write_long(self.frame_max);
+            writer.write_short(self.heartbeat);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Tune> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let channel_max = try!(reader.read_short("channel_max"));
+            let frame_max = try!(reader.read_long("frame_max"));
+            let heartbeat = try!(reader.read_short("heartbeat"));
+            try!(reader.finish());
+            Ok(Tune {
+                cid: cid,
+                id: id,
+                channel_max: channel_max,
+                frame_max: frame_max,
+                heartbeat: heartbeat,
+            })
         }
     }
 
@@ -197,7 +520,7 @@ pub mod connection {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct TuneOk {
         cid: Short,
         id: Short,
@@ -207,10 +530,31 @@ pub mod connection {
     }
 
     impl Method for TuneOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.channel_max);
+            writer.write_long(self.frame_max);
+            writer.write_short(self.heartbeat);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<TuneOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let channel_max = try!(reader.read_short("channel_max"));
+            let frame_max = try!(reader.read_long("frame_max"));
+            let heartbeat = try!(reader.read_short("heartbeat"));
+            try!(reader.finish());
+            Ok(TuneOk {
+                cid: cid,
+                id: id,
+                channel_max: channel_max,
+                frame_max: frame_max,
+                heartbeat: heartbeat,
+            })
         }
     }
 
@@ -226,7 +570,7 @@ pub mod connection {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Open {
         cid: Short,
         id: Short,
@@ -236,12 +580,32 @@ pub mod connection {
     }
 
     impl Method for Open {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "virtual_host" => Some(StrType::Short),
-                "capabilities" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            try!(writer.write_short_str(&self.virtual_host));
+            try!(writer.write_short_str(&self.capabilities));
+            writer.write_bits(&[self.insist]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Open> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let virtual_host = try!(reader.read_short_str("virtual_host"));
+            let capabilities = try!(reader.read_short_str("capabilities"));
+            let bits = try!(reader.read_octet("insist"));
+            let insist = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(Open {
+                cid: cid,
+                id: id,
+                virtual_host: virtual_host,
+                capabilities: capabilities,
+                insist: insist,
+            })
         }
     }
 
@@ -257,7 +621,48 @@ pub mod connection {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct OpenRef<'a> {
+        cid: Short,
+        id: Short,
+        pub virtual_host: &'a str,
+        pub capabilities: &'a str,
+        pub insist: Bit,
+    }
+
+    impl<'a> MethodRef<'a> for OpenRef<'a> {
+        type Owned = Open;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<OpenRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let virtual_host = try!(reader.read_short_str_ref("virtual_host"));
+            let capabilities = try!(reader.read_short_str_ref("capabilities"));
+            let bits = try!(reader.read_octet("insist"));
+            let insist = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(OpenRef {
+                cid: cid,
+                id: id,
+                virtual_host: virtual_host,
+                capabilities: capabilities,
+                insist: insist,
+            })
+        }
+
+        fn into_owned(self) -> Open {
+            Open {
+                cid: self.cid,
+                id: self.id,
+                virtual_host: self.virtual_host.to_string(),
+                capabilities: self.capabilities.to_string(),
+                insist: self.insist,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct OpenOk {
         cid: Short,
         id: Short,
@@ -265,11 +670,25 @@ pub mod connection {
     }
 
     impl Method for OpenOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "known_hosts" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            try!(writer.write_short_str(&self.known_hosts));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<OpenOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let known_hosts = try!(reader.read_short_str("known_hosts"));
+            try!(reader.finish());
+            Ok(OpenOk {
+                cid: cid,
+                id: id,
+                known_hosts: known_hosts,
+            })
         }
     }
 
@@ -283,7 +702,39 @@ pub mod connection {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct OpenOkRef<'a> {
+        cid: Short,
+        id: Short,
+        pub known_hosts: &'a str,
+    }
+
+    impl<'a> MethodRef<'a> for OpenOkRef<'a> {
+        type Owned = OpenOk;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<OpenOkRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let known_hosts = try!(reader.read_short_str_ref("known_hosts"));
+            try!(reader.finish());
+            Ok(OpenOkRef {
+                cid: cid,
+                id: id,
+                known_hosts: known_hosts,
+            })
+        }
+
+        fn into_owned(self) -> OpenOk {
+            OpenOk {
+                cid: self.cid,
+                id: self.id,
+                known_hosts: self.known_hosts.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct Close {
         cid: Short,
         id: Short,
@@ -294,11 +745,34 @@ pub mod connection {
     }
 
     impl Method for Close {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "reply_text" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.reply_code);
+            try!(writer.write_short_str(&self.reply_text));
+            writer.write_short(self.class_id);
+            writer.write_short(self.method_id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Close> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let reply_code = try!(reader.read_short("reply_code"));
+            let reply_text = try!(reader.read_short_str("reply_text"));
+            let class_id = try!(reader.read_short("class_id"));
+            let method_id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(Close {
+                cid: cid,
+                id: id,
+                reply_code: reply_code,
+                reply_text: reply_text,
+                class_id: class_id,
+                method_id: method_id,
+            })
         }
     }
 
@@ -315,17 +789,73 @@ pub mod connection {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct CloseRef<'a> {
+        cid: Short,
+        id: Short,
+        pub reply_code: Short,
+        pub reply_text: &'a str,
+        pub class_id: Short,
+        pub method_id: Short,
+    }
+
+    impl<'a> MethodRef<'a> for CloseRef<'a> {
+        type Owned = Close;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<CloseRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let reply_code = try!(reader.read_short("reply_code"));
+            let reply_text = try!(reader.read_short_str_ref("reply_text"));
+            let class_id = try!(reader.read_short("class_id"));
+            let method_id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(CloseRef {
+                cid: cid,
+                id: id,
+                reply_code: reply_code,
+                reply_text: reply_text,
+                class_id: class_id,
+                method_id: method_id,
+            })
+        }
+
+        fn into_owned(self) -> Close {
+            Close {
+                cid: self.cid,
+                id: self.id,
+                reply_code: self.reply_code,
+                reply_text: self.reply_text.to_string(),
+                class_id: self.class_id,
+                method_id: self.method_id,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct CloseOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for CloseOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<CloseOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(CloseOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -338,7 +868,7 @@ pub mod connection {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Blocked {
         cid: Short,
         id: Short,
@@ -346,11 +876,25 @@ pub mod connection {
     }
 
     impl Method for Blocked {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "reason" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            try!(writer.write_short_str(&self.reason));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Blocked> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let reason = try!(reader.read_short_str("reason"));
+            try!(reader.finish());
+            Ok(Blocked {
+                cid: cid,
+                id: id,
+                reason: reason,
+            })
         }
     }
 
@@ -364,17 +908,61 @@ pub mod connection {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct BlockedRef<'a> {
+        cid: Short,
+        id: Short,
+        pub reason: &'a str,
+    }
+
+    impl<'a> MethodRef<'a> for BlockedRef<'a> {
+        type Owned = Blocked;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<BlockedRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let reason = try!(reader.read_short_str_ref("reason"));
+            try!(reader.finish());
+            Ok(BlockedRef {
+                cid: cid,
+                id: id,
+                reason: reason,
+            })
+        }
+
+        fn into_owned(self) -> Blocked {
+            Blocked {
+                cid: self.cid,
+                id: self.id,
+                reason: self.reason.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct Unblocked {
         cid: Short,
         id: Short,
     }
 
     impl Method for Unblocked {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Unblocked> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(Unblocked {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -389,10 +977,11 @@ pub mod connection {
 }
 
 pub mod channel {
-    use method::Method;
+    use method::{Method, MethodRef, Reader, Writer};
+    use result::*;
     use types::*;
     
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Open {
         cid: Short,
         id: Short,
@@ -400,11 +989,25 @@ pub mod channel {
     }
 
     impl Method for Open {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "out_of_band" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            try!(writer.write_short_str(&self.out_of_band));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Open> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let out_of_band = try!(reader.read_short_str("out_of_band"));
+            try!(reader.finish());
+            Ok(Open {
+                cid: cid,
+                id: id,
+                out_of_band: out_of_band,
+            })
         }
     }
 
@@ -418,7 +1021,39 @@ pub mod channel {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct OpenRef<'a> {
+        cid: Short,
+        id: Short,
+        pub out_of_band: &'a str,
+    }
+
+    impl<'a> MethodRef<'a> for OpenRef<'a> {
+        type Owned = Open;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<OpenRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let out_of_band = try!(reader.read_short_str_ref("out_of_band"));
+            try!(reader.finish());
+            Ok(OpenRef {
+                cid: cid,
+                id: id,
+                out_of_band: out_of_band,
+            })
+        }
+
+        fn into_owned(self) -> Open {
+            Open {
+                cid: self.cid,
+                id: self.id,
+                out_of_band: self.out_of_band.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct OpenOk {
         cid: Short,
         id: Short,
@@ -426,11 +1061,25 @@ pub mod channel {
     }
 
     impl Method for OpenOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "channel_id" => Some(StrType::Long),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_long_str(&self.channel_id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<OpenOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let channel_id = try!(reader.read_long_str("channel_id"));
+            try!(reader.finish());
+            Ok(OpenOk {
+                cid: cid,
+                id: id,
+                channel_id: channel_id,
+            })
         }
     }
 
@@ -444,7 +1093,39 @@ pub mod channel {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct OpenOkRef<'a> {
+        cid: Short,
+        id: Short,
+        pub channel_id: &'a [u8],
+    }
+
+    impl<'a> MethodRef<'a> for OpenOkRef<'a> {
+        type Owned = OpenOk;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<OpenOkRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let channel_id = try!(reader.read_long_str_ref("channel_id"));
+            try!(reader.finish());
+            Ok(OpenOkRef {
+                cid: cid,
+                id: id,
+                channel_id: channel_id,
+            })
+        }
+
+        fn into_owned(self) -> OpenOk {
+            OpenOk {
+                cid: self.cid,
+                id: self.id,
+                channel_id: self.channel_id.to_vec(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct Flow {
         cid: Short,
         id: Short,
@@ -452,10 +1133,26 @@ pub mod channel {
     }
 
     impl Method for Flow {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_bits(&[self.active]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Flow> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let bits = try!(reader.read_octet("active"));
+            let active = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(Flow {
+                cid: cid,
+                id: id,
+                active: active,
+            })
         }
     }
 
@@ -469,7 +1166,7 @@ pub mod channel {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct FlowOk {
         cid: Short,
         id: Short,
@@ -477,10 +1174,26 @@ pub mod channel {
     }
 
     impl Method for FlowOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_bits(&[self.active]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<FlowOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let bits = try!(reader.read_octet("active"));
+            let active = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(FlowOk {
+                cid: cid,
+                id: id,
+                active: active,
+            })
         }
     }
 
@@ -494,7 +1207,7 @@ pub mod channel {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Close {
         cid: Short,
         id: Short,
@@ -505,11 +1218,34 @@ pub mod channel {
     }
 
     impl Method for Close {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "reply_text" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.reply_code);
+            try!(writer.write_short_str(&self.reply_text));
+            writer.write_short(self.class_id);
+            writer.write_short(self.method_id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Close> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let reply_code = try!(reader.read_short("reply_code"));
+            let reply_text = try!(reader.read_short_str("reply_text"));
+            let class_id = try!(reader.read_short("class_id"));
+            let method_id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(Close {
+                cid: cid,
+                id: id,
+                reply_code: reply_code,
+                reply_text: reply_text,
+                class_id: class_id,
+                method_id: method_id,
+            })
         }
     }
 
@@ -526,17 +1262,73 @@ pub mod channel {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct CloseRef<'a> {
+        cid: Short,
+        id: Short,
+        pub reply_code: Short,
+        pub reply_text: &'a str,
+        pub class_id: Short,
+        pub method_id: Short,
+    }
+
+    impl<'a> MethodRef<'a> for CloseRef<'a> {
+        type Owned = Close;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<CloseRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let reply_code = try!(reader.read_short("reply_code"));
+            let reply_text = try!(reader.read_short_str_ref("reply_text"));
+            let class_id = try!(reader.read_short("class_id"));
+            let method_id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(CloseRef {
+                cid: cid,
+                id: id,
+                reply_code: reply_code,
+                reply_text: reply_text,
+                class_id: class_id,
+                method_id: method_id,
+            })
+        }
+
+        fn into_owned(self) -> Close {
+            Close {
+                cid: self.cid,
+                id: self.id,
+                reply_code: self.reply_code,
+                reply_text: self.reply_text.to_string(),
+                class_id: self.class_id,
+                method_id: self.method_id,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct CloseOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for CloseOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<CloseOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(CloseOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -551,10 +1343,11 @@ pub mod channel {
 }
 
 pub mod access {
-    use method::Method;
+    use method::{Method, MethodRef, Reader, Writer};
+    use result::*;
     use types::*;
     
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Request {
         cid: Short,
         id: Short,
@@ -567,11 +1360,37 @@ pub mod access {
     }
 
     impl Method for Request {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "realm" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            try!(writer.write_short_str(&self.realm));
+            writer.write_bits(&[self.exclusive, self.passive, self.active, self.write, self.read]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Request> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let realm = try!(reader.read_short_str("realm"));
+            let bits = try!(reader.read_octet("exclusive"));
+            let exclusive = bits & 0x01 != 0;
+            let passive = bits & 0x02 != 0;
+            let active = bits & 0x04 != 0;
+            let write = bits & 0x08 != 0;
+            let read = bits & 0x10 != 0;
+            try!(reader.finish());
+            Ok(Request {
+                cid: cid,
+                id: id,
+                realm: realm,
+                exclusive: exclusive,
+                passive: passive,
+                active: active,
+                write: write,
+                read: read,
+            })
         }
     }
 
@@ -590,7 +1409,60 @@ pub mod access {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct RequestRef<'a> {
+        cid: Short,
+        id: Short,
+        pub realm: &'a str,
+        pub exclusive: Bit,
+        pub passive: Bit,
+        pub active: Bit,
+        pub write: Bit,
+        pub read: Bit,
+    }
+
+    impl<'a> MethodRef<'a> for RequestRef<'a> {
+        type Owned = Request;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<RequestRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let realm = try!(reader.read_short_str_ref("realm"));
+            let bits = try!(reader.read_octet("exclusive"));
+            let exclusive = bits & 0x01 != 0;
+            let passive = bits & 0x02 != 0;
+            let active = bits & 0x04 != 0;
+            let write = bits & 0x08 != 0;
+            let read = bits & 0x10 != 0;
+            try!(reader.finish());
+            Ok(RequestRef {
+                cid: cid,
+                id: id,
+                realm: realm,
+                exclusive: exclusive,
+                passive: passive,
+                active: active,
+                write: write,
+                read: read,
+            })
+        }
+
+        fn into_owned(self) -> Request {
+            Request {
+                cid: self.cid,
+                id: self.id,
+                realm: self.realm.to_string(),
+                exclusive: self.exclusive,
+                passive: self.passive,
+                active: self.active,
+                write: self.write,
+                read: self.read,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct RequestOk {
         cid: Short,
         id: Short,
@@ -598,10 +1470,25 @@ pub mod access {
     }
 
     impl Method for RequestOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<RequestOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            try!(reader.finish());
+            Ok(RequestOk {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+            })
         }
     }
 
@@ -617,10 +1504,11 @@ pub mod access {
 }
 
 pub mod exchange {
-    use method::Method;
+    use method::{Method, MethodRef, Reader, Writer};
+    use result::*;
     use types::*;
     
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Declare {
         cid: Short,
         id: Short,
@@ -636,12 +1524,46 @@ pub mod exchange {
     }
 
     impl Method for Declare {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "exchange" => Some(StrType::Short),
-                "ty" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            try!(writer.write_short_str(&self.exchange));
+            try!(writer.write_short_str(&self.ty));
+            writer.write_bits(&[self.passive, self.durable, self.auto_delete, self.internal, self.nowait]);
+            try!(writer.write_table(&self.arguments));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Declare> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let exchange = try!(reader.read_short_str("exchange"));
+            let ty = try!(reader.read_short_str("ty"));
+            let bits = try!(reader.read_octet("passive"));
+            let passive = bits & 0x01 != 0;
+            let durable = bits & 0x02 != 0;
+            let auto_delete = bits & 0x04 != 0;
+            let internal = bits & 0x08 != 0;
+            let nowait = bits & 0x10 != 0;
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(Declare {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                exchange: exchange,
+                ty: ty,
+                passive: passive,
+                durable: durable,
+                auto_delete: auto_delete,
+                internal: internal,
+                nowait: nowait,
+                arguments: arguments,
+            })
         }
     }
 
@@ -663,17 +1585,94 @@ pub mod exchange {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct DeclareRef<'a> {
+        cid: Short,
+        id: Short,
+        pub ticket: Short,
+        pub exchange: &'a str,
+        pub ty: &'a str,
+        pub passive: Bit,
+        pub durable: Bit,
+        pub auto_delete: Bit,
+        pub internal: Bit,
+        pub nowait: Bit,
+        pub arguments: Table,
+    }
+
+    impl<'a> MethodRef<'a> for DeclareRef<'a> {
+        type Owned = Declare;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<DeclareRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let exchange = try!(reader.read_short_str_ref("exchange"));
+            let ty = try!(reader.read_short_str_ref("ty"));
+            let bits = try!(reader.read_octet("passive"));
+            let passive = bits & 0x01 != 0;
+            let durable = bits & 0x02 != 0;
+            let auto_delete = bits & 0x04 != 0;
+            let internal = bits & 0x08 != 0;
+            let nowait = bits & 0x10 != 0;
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(DeclareRef {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                exchange: exchange,
+                ty: ty,
+                passive: passive,
+                durable: durable,
+                auto_delete: auto_delete,
+                internal: internal,
+                nowait: nowait,
+                arguments: arguments,
+            })
+        }
+
+        fn into_owned(self) -> Declare {
+            Declare {
+                cid: self.cid,
+                id: self.id,
+                ticket: self.ticket,
+                exchange: self.exchange.to_string(),
+                ty: self.ty.to_string(),
+                passive: self.passive,
+                durable: self.durable,
+                auto_delete: self.auto_delete,
+                internal: self.internal,
+                nowait: self.nowait,
+                arguments: self.arguments,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct DeclareOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for DeclareOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<DeclareOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(DeclareOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -686,7 +1685,7 @@ pub mod exchange {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Delete {
         cid: Short,
         id: Short,
@@ -697,11 +1696,34 @@ pub mod exchange {
     }
 
     impl Method for Delete {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "exchange" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            try!(writer.write_short_str(&self.exchange));
+            writer.write_bits(&[self.if_unused, self.nowait]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Delete> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let exchange = try!(reader.read_short_str("exchange"));
+            let bits = try!(reader.read_octet("if_unused"));
+            let if_unused = bits & 0x01 != 0;
+            let nowait = bits & 0x02 != 0;
+            try!(reader.finish());
+            Ok(Delete {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                exchange: exchange,
+                if_unused: if_unused,
+                nowait: nowait,
+            })
         }
     }
 
@@ -718,17 +1740,74 @@ pub mod exchange {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct DeleteRef<'a> {
+        cid: Short,
+        id: Short,
+        pub ticket: Short,
+        pub exchange: &'a str,
+        pub if_unused: Bit,
+        pub nowait: Bit,
+    }
+
+    impl<'a> MethodRef<'a> for DeleteRef<'a> {
+        type Owned = Delete;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<DeleteRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let exchange = try!(reader.read_short_str_ref("exchange"));
+            let bits = try!(reader.read_octet("if_unused"));
+            let if_unused = bits & 0x01 != 0;
+            let nowait = bits & 0x02 != 0;
+            try!(reader.finish());
+            Ok(DeleteRef {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                exchange: exchange,
+                if_unused: if_unused,
+                nowait: nowait,
+            })
+        }
+
+        fn into_owned(self) -> Delete {
+            Delete {
+                cid: self.cid,
+                id: self.id,
+                ticket: self.ticket,
+                exchange: self.exchange.to_string(),
+                if_unused: self.if_unused,
+                nowait: self.nowait,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct DeleteOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for DeleteOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<DeleteOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(DeleteOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -741,7 +1820,7 @@ pub mod exchange {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Bind {
         cid: Short,
         id: Short,
@@ -754,13 +1833,41 @@ pub mod exchange {
     }
 
     impl Method for Bind {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "destination" => Some(StrType::Short),
-                "source" => Some(StrType::Short),
-                "routing_key" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            try!(writer.write_short_str(&self.destination));
+            try!(writer.write_short_str(&self.source));
+            try!(writer.write_short_str(&self.routing_key));
+            writer.write_bits(&[self.nowait]);
+            try!(writer.write_table(&self.arguments));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Bind> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let destination = try!(reader.read_short_str("destination"));
+            let source = try!(reader.read_short_str("source"));
+            let routing_key = try!(reader.read_short_str("routing_key"));
+            let bits = try!(reader.read_octet("nowait"));
+            let nowait = bits & 0x01 != 0;
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(Bind {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                destination: destination,
+                source: source,
+                routing_key: routing_key,
+                nowait: nowait,
+                arguments: arguments,
+            })
         }
     }
 
@@ -779,17 +1886,82 @@ pub mod exchange {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct BindRef<'a> {
+        cid: Short,
+        id: Short,
+        pub ticket: Short,
+        pub destination: &'a str,
+        pub source: &'a str,
+        pub routing_key: &'a str,
+        pub nowait: Bit,
+        pub arguments: Table,
+    }
+
+    impl<'a> MethodRef<'a> for BindRef<'a> {
+        type Owned = Bind;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<BindRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let destination = try!(reader.read_short_str_ref("destination"));
+            let source = try!(reader.read_short_str_ref("source"));
+            let routing_key = try!(reader.read_short_str_ref("routing_key"));
+            let bits = try!(reader.read_octet("nowait"));
+            let nowait = bits & 0x01 != 0;
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(BindRef {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                destination: destination,
+                source: source,
+                routing_key: routing_key,
+                nowait: nowait,
+                arguments: arguments,
+            })
+        }
+
+        fn into_owned(self) -> Bind {
+            Bind {
+                cid: self.cid,
+                id: self.id,
+                ticket: self.ticket,
+                destination: self.destination.to_string(),
+                source: self.source.to_string(),
+                routing_key: self.routing_key.to_string(),
+                nowait: self.nowait,
+                arguments: self.arguments,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct BindOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for BindOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<BindOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(BindOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -802,7 +1974,7 @@ pub mod exchange {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Unbind {
         cid: Short,
         id: Short,
@@ -815,13 +1987,41 @@ pub mod exchange {
     }
 
     impl Method for Unbind {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "destination" => Some(StrType::Short),
-                "source" => Some(StrType::Short),
-                "routing_key" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            try!(writer.write_short_str(&self.destination));
+            try!(writer.write_short_str(&self.source));
+            try!(writer.write_short_str(&self.routing_key));
+            writer.write_bits(&[self.nowait]);
+            try!(writer.write_table(&self.arguments));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Unbind> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let destination = try!(reader.read_short_str("destination"));
+            let source = try!(reader.read_short_str("source"));
+            let routing_key = try!(reader.read_short_str("routing_key"));
+            let bits = try!(reader.read_octet("nowait"));
+            let nowait = bits & 0x01 != 0;
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(Unbind {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                destination: destination,
+                source: source,
+                routing_key: routing_key,
+                nowait: nowait,
+                arguments: arguments,
+            })
         }
     }
 
@@ -840,17 +2040,82 @@ pub mod exchange {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct UnbindRef<'a> {
+        cid: Short,
+        id: Short,
+        pub ticket: Short,
+        pub destination: &'a str,
+        pub source: &'a str,
+        pub routing_key: &'a str,
+        pub nowait: Bit,
+        pub arguments: Table,
+    }
+
+    impl<'a> MethodRef<'a> for UnbindRef<'a> {
+        type Owned = Unbind;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<UnbindRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let destination = try!(reader.read_short_str_ref("destination"));
+            let source = try!(reader.read_short_str_ref("source"));
+            let routing_key = try!(reader.read_short_str_ref("routing_key"));
+            let bits = try!(reader.read_octet("nowait"));
+            let nowait = bits & 0x01 != 0;
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(UnbindRef {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                destination: destination,
+                source: source,
+                routing_key: routing_key,
+                nowait: nowait,
+                arguments: arguments,
+            })
+        }
+
+        fn into_owned(self) -> Unbind {
+            Unbind {
+                cid: self.cid,
+                id: self.id,
+                ticket: self.ticket,
+                destination: self.destination.to_string(),
+                source: self.source.to_string(),
+                routing_key: self.routing_key.to_string(),
+                nowait: self.nowait,
+                arguments: self.arguments,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct UnbindOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for UnbindOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<UnbindOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(UnbindOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -865,10 +2130,11 @@ pub mod exchange {
 }
 
 pub mod queue {
-    use method::Method;
+    use method::{Method, MethodRef, Reader, Writer};
+    use result::*;
     use types::*;
     
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Declare {
         cid: Short,
         id: Short,
@@ -883,11 +2149,43 @@ pub mod queue {
     }
 
     impl Method for Declare {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "queue" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            try!(writer.write_short_str(&self.queue));
+            writer.write_bits(&[self.passive, self.durable, self.exclusive, self.auto_delete, self.nowait]);
+            try!(writer.write_table(&self.arguments));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Declare> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str("queue"));
+            let bits = try!(reader.read_octet("passive"));
+            let passive = bits & 0x01 != 0;
+            let durable = bits & 0x02 != 0;
+            let exclusive = bits & 0x04 != 0;
+            let auto_delete = bits & 0x08 != 0;
+            let nowait = bits & 0x10 != 0;
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(Declare {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                passive: passive,
+                durable: durable,
+                exclusive: exclusive,
+                auto_delete: auto_delete,
+                nowait: nowait,
+                arguments: arguments,
+            })
         }
     }
 
@@ -908,7 +2206,68 @@ pub mod queue {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct DeclareRef<'a> {
+        cid: Short,
+        id: Short,
+        pub ticket: Short,
+        pub queue: &'a str,
+        pub passive: Bit,
+        pub durable: Bit,
+        pub exclusive: Bit,
+        pub auto_delete: Bit,
+        pub nowait: Bit,
+        pub arguments: Table,
+    }
+
+    impl<'a> MethodRef<'a> for DeclareRef<'a> {
+        type Owned = Declare;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<DeclareRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str_ref("queue"));
+            let bits = try!(reader.read_octet("passive"));
+            let passive = bits & 0x01 != 0;
+            let durable = bits & 0x02 != 0;
+            let exclusive = bits & 0x04 != 0;
+            let auto_delete = bits & 0x08 != 0;
+            let nowait = bits & 0x10 != 0;
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(DeclareRef {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                passive: passive,
+                durable: durable,
+                exclusive: exclusive,
+                auto_delete: auto_delete,
+                nowait: nowait,
+                arguments: arguments,
+            })
+        }
+
+        fn into_owned(self) -> Declare {
+            Declare {
+                cid: self.cid,
+                id: self.id,
+                ticket: self.ticket,
+                queue: self.queue.to_string(),
+                passive: self.passive,
+                durable: self.durable,
+                exclusive: self.exclusive,
+                auto_delete: self.auto_delete,
+                nowait: self.nowait,
+                arguments: self.arguments,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct DeclareOk {
         cid: Short,
         id: Short,
@@ -918,11 +2277,31 @@ pub mod queue {
     }
 
     impl Method for DeclareOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "queue" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            try!(writer.write_short_str(&self.queue));
+            writer.write_long(self.message_count);
+            writer.write_long(self.consumer_count);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<DeclareOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let queue = try!(reader.read_short_str("queue"));
+            let message_count = try!(reader.read_long("message_count"));
+            let consumer_count = try!(reader.read_long("consumer_count"));
+            try!(reader.finish());
+            Ok(DeclareOk {
+                cid: cid,
+                id: id,
+                queue: queue,
+                message_count: message_count,
+                consumer_count: consumer_count,
+            })
         }
     }
 
@@ -938,7 +2317,47 @@ pub mod queue {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct DeclareOkRef<'a> {
+        cid: Short,
+        id: Short,
+        pub queue: &'a str,
+        pub message_count: Long,
+        pub consumer_count: Long,
+    }
+
+    impl<'a> MethodRef<'a> for DeclareOkRef<'a> {
+        type Owned = DeclareOk;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<DeclareOkRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let queue = try!(reader.read_short_str_ref("queue"));
+            let message_count = try!(reader.read_long("message_count"));
+            let consumer_count = try!(reader.read_long("consumer_count"));
+            try!(reader.finish());
+            Ok(DeclareOkRef {
+                cid: cid,
+                id: id,
+                queue: queue,
+                message_count: message_count,
+                consumer_count: consumer_count,
+            })
+        }
+
+        fn into_owned(self) -> DeclareOk {
+            DeclareOk {
+                cid: self.cid,
+                id: self.id,
+                queue: self.queue.to_string(),
+                message_count: self.message_count,
+                consumer_count: self.consumer_count,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct Bind {
         cid: Short,
         id: Short,
@@ -951,13 +2370,41 @@ pub mod queue {
     }
 
     impl Method for Bind {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "queue" => Some(StrType::Short),
-                "exchange" => Some(StrType::Short),
-                "routing_key" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            try!(writer.write_short_str(&self.queue));
+            try!(writer.write_short_str(&self.exchange));
+            try!(writer.write_short_str(&self.routing_key));
+            writer.write_bits(&[self.nowait]);
+            try!(writer.write_table(&self.arguments));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Bind> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str("queue"));
+            let exchange = try!(reader.read_short_str("exchange"));
+            let routing_key = try!(reader.read_short_str("routing_key"));
+            let bits = try!(reader.read_octet("nowait"));
+            let nowait = bits & 0x01 != 0;
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(Bind {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                exchange: exchange,
+                routing_key: routing_key,
+                nowait: nowait,
+                arguments: arguments,
+            })
         }
     }
 
@@ -976,17 +2423,82 @@ pub mod queue {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct BindRef<'a> {
+        cid: Short,
+        id: Short,
+        pub ticket: Short,
+        pub queue: &'a str,
+        pub exchange: &'a str,
+        pub routing_key: &'a str,
+        pub nowait: Bit,
+        pub arguments: Table,
+    }
+
+    impl<'a> MethodRef<'a> for BindRef<'a> {
+        type Owned = Bind;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<BindRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str_ref("queue"));
+            let exchange = try!(reader.read_short_str_ref("exchange"));
+            let routing_key = try!(reader.read_short_str_ref("routing_key"));
+            let bits = try!(reader.read_octet("nowait"));
+            let nowait = bits & 0x01 != 0;
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(BindRef {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                exchange: exchange,
+                routing_key: routing_key,
+                nowait: nowait,
+                arguments: arguments,
+            })
+        }
+
+        fn into_owned(self) -> Bind {
+            Bind {
+                cid: self.cid,
+                id: self.id,
+                ticket: self.ticket,
+                queue: self.queue.to_string(),
+                exchange: self.exchange.to_string(),
+                routing_key: self.routing_key.to_string(),
+                nowait: self.nowait,
+                arguments: self.arguments,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct BindOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for BindOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<BindOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(BindOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -999,7 +2511,7 @@ pub mod queue {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Purge {
         cid: Short,
         id: Short,
@@ -1009,11 +2521,32 @@ pub mod queue {
     }
 
     impl Method for Purge {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "queue" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            try!(writer.write_short_str(&self.queue));
+            writer.write_bits(&[self.nowait]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Purge> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str("queue"));
+            let bits = try!(reader.read_octet("nowait"));
+            let nowait = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(Purge {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                nowait: nowait,
+            })
         }
     }
 
@@ -1029,7 +2562,48 @@ pub mod queue {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct PurgeRef<'a> {
+        cid: Short,
+        id: Short,
+        pub ticket: Short,
+        pub queue: &'a str,
+        pub nowait: Bit,
+    }
+
+    impl<'a> MethodRef<'a> for PurgeRef<'a> {
+        type Owned = Purge;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<PurgeRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str_ref("queue"));
+            let bits = try!(reader.read_octet("nowait"));
+            let nowait = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(PurgeRef {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                nowait: nowait,
+            })
+        }
+
+        fn into_owned(self) -> Purge {
+            Purge {
+                cid: self.cid,
+                id: self.id,
+                ticket: self.ticket,
+                queue: self.queue.to_string(),
+                nowait: self.nowait,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct PurgeOk {
         cid: Short,
         id: Short,
@@ -1037,10 +2611,25 @@ pub mod queue {
     }
 
     impl Method for PurgeOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_long(self.message_count);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<PurgeOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let message_count = try!(reader.read_long("message_count"));
+            try!(reader.finish());
+            Ok(PurgeOk {
+                cid: cid,
+                id: id,
+                message_count: message_count,
+            })
         }
     }
 
@@ -1054,7 +2643,7 @@ pub mod queue {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Delete {
         cid: Short,
         id: Short,
@@ -1066,11 +2655,36 @@ pub mod queue {
     }
 
     impl Method for Delete {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "queue" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            try!(writer.write_short_str(&self.queue));
+            writer.write_bits(&[self.if_unused, self.if_empty, self.nowait]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Delete> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str("queue"));
+            let bits = try!(reader.read_octet("if_unused"));
+            let if_unused = bits & 0x01 != 0;
+            let if_empty = bits & 0x02 != 0;
+            let nowait = bits & 0x04 != 0;
+            try!(reader.finish());
+            Ok(Delete {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                if_unused: if_unused,
+                if_empty: if_empty,
+                nowait: nowait,
+            })
         }
     }
 
@@ -1088,7 +2702,56 @@ pub mod queue {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct DeleteRef<'a> {
+        cid: Short,
+        id: Short,
+        pub ticket: Short,
+        pub queue: &'a str,
+        pub if_unused: Bit,
+        pub if_empty: Bit,
+        pub nowait: Bit,
+    }
+
+    impl<'a> MethodRef<'a> for DeleteRef<'a> {
+        type Owned = Delete;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<DeleteRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str_ref("queue"));
+            let bits = try!(reader.read_octet("if_unused"));
+            let if_unused = bits & 0x01 != 0;
+            let if_empty = bits & 0x02 != 0;
+            let nowait = bits & 0x04 != 0;
+            try!(reader.finish());
+            Ok(DeleteRef {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                if_unused: if_unused,
+                if_empty: if_empty,
+                nowait: nowait,
+            })
+        }
+
+        fn into_owned(self) -> Delete {
+            Delete {
+                cid: self.cid,
+                id: self.id,
+                ticket: self.ticket,
+                queue: self.queue.to_string(),
+                if_unused: self.if_unused,
+                if_empty: self.if_empty,
+                nowait: self.nowait,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct DeleteOk {
         cid: Short,
         id: Short,
@@ -1096,10 +2759,25 @@ pub mod queue {
     }
 
     impl Method for DeleteOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_long(self.message_count);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<DeleteOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let message_count = try!(reader.read_long("message_count"));
+            try!(reader.finish());
+            Ok(DeleteOk {
+                cid: cid,
+                id: id,
+                message_count: message_count,
+            })
         }
     }
 
@@ -1113,7 +2791,7 @@ pub mod queue {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Unbind {
         cid: Short,
         id: Short,
@@ -1125,13 +2803,37 @@ pub mod queue {
     }
 
     impl Method for Unbind {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "queue" => Some(StrType::Short),
-                "exchange" => Some(StrType::Short),
-                "routing_key" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            try!(writer.write_short_str(&self.queue));
+            try!(writer.write_short_str(&self.exchange));
+            try!(writer.write_short_str(&self.routing_key));
+            try!(writer.write_table(&self.arguments));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Unbind> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str("queue"));
+            let exchange = try!(reader.read_short_str("exchange"));
+            let routing_key = try!(reader.read_short_str("routing_key"));
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(Unbind {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                exchange: exchange,
+                routing_key: routing_key,
+                arguments: arguments,
+            })
         }
     }
 
@@ -1149,17 +2851,77 @@ pub mod queue {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct UnbindRef<'a> {
+        cid: Short,
+        id: Short,
+        pub ticket: Short,
+        pub queue: &'a str,
+        pub exchange: &'a str,
+        pub routing_key: &'a str,
+        pub arguments: Table,
+    }
+
+    impl<'a> MethodRef<'a> for UnbindRef<'a> {
+        type Owned = Unbind;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<UnbindRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str_ref("queue"));
+            let exchange = try!(reader.read_short_str_ref("exchange"));
+            let routing_key = try!(reader.read_short_str_ref("routing_key"));
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(UnbindRef {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                exchange: exchange,
+                routing_key: routing_key,
+                arguments: arguments,
+            })
+        }
+
+        fn into_owned(self) -> Unbind {
+            Unbind {
+                cid: self.cid,
+                id: self.id,
+                ticket: self.ticket,
+                queue: self.queue.to_string(),
+                exchange: self.exchange.to_string(),
+                routing_key: self.routing_key.to_string(),
+                arguments: self.arguments,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct UnbindOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for UnbindOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<UnbindOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(UnbindOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -1174,10 +2936,11 @@ pub mod queue {
 }
 
 pub mod basic {
-    use method::Method;
+    use method::{Method, MethodRef, Reader, Writer};
+    use result::*;
     use types::*;
     
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Qos {
         cid: Short,
         id: Short,
@@ -1187,10 +2950,32 @@ pub mod basic {
     }
 
     impl Method for Qos {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_long(self.prefetch_size);
+            writer.write_short(self.prefetch_count);
+            writer.write_bits(&[self.global]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Qos> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let prefetch_size = try!(reader.read_long("prefetch_size"));
+            let prefetch_count = try!(reader.read_short("prefetch_count"));
+            let bits = try!(reader.read_octet("global"));
+            let global = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(Qos {
+                cid: cid,
+                id: id,
+                prefetch_size: prefetch_size,
+                prefetch_count: prefetch_count,
+                global: global,
+            })
         }
     }
 
@@ -1206,17 +2991,29 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct QosOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for QosOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<QosOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(QosOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -1229,7 +3026,7 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Consume {
         cid: Short,
         id: Short,
@@ -1244,12 +3041,44 @@ pub mod basic {
     }
 
     impl Method for Consume {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "queue" => Some(StrType::Short),
-                "consumer_tag" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            try!(writer.write_short_str(&self.queue));
+            try!(writer.write_short_str(&self.consumer_tag));
+            writer.write_bits(&[self.no_local, self.no_ack, self.exclusive, self.nowait]);
+            try!(writer.write_table(&self.arguments));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Consume> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str("queue"));
+            let consumer_tag = try!(reader.read_short_str("consumer_tag"));
+            let bits = try!(reader.read_octet("no_local"));
+            let no_local = bits & 0x01 != 0;
+            let no_ack = bits & 0x02 != 0;
+            let exclusive = bits & 0x04 != 0;
+            let nowait = bits & 0x08 != 0;
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(Consume {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                consumer_tag: consumer_tag,
+                no_local: no_local,
+                no_ack: no_ack,
+                exclusive: exclusive,
+                nowait: nowait,
+                arguments: arguments,
+            })
         }
     }
 
@@ -1270,7 +3099,68 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct ConsumeRef<'a> {
+        cid: Short,
+        id: Short,
+        pub ticket: Short,
+        pub queue: &'a str,
+        pub consumer_tag: &'a str,
+        pub no_local: Bit,
+        pub no_ack: Bit,
+        pub exclusive: Bit,
+        pub nowait: Bit,
+        pub arguments: Table,
+    }
+
+    impl<'a> MethodRef<'a> for ConsumeRef<'a> {
+        type Owned = Consume;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<ConsumeRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str_ref("queue"));
+            let consumer_tag = try!(reader.read_short_str_ref("consumer_tag"));
+            let bits = try!(reader.read_octet("no_local"));
+            let no_local = bits & 0x01 != 0;
+            let no_ack = bits & 0x02 != 0;
+            let exclusive = bits & 0x04 != 0;
+            let nowait = bits & 0x08 != 0;
+            let arguments = try!(reader.read_table("arguments"));
+            try!(reader.finish());
+            Ok(ConsumeRef {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                consumer_tag: consumer_tag,
+                no_local: no_local,
+                no_ack: no_ack,
+                exclusive: exclusive,
+                nowait: nowait,
+                arguments: arguments,
+            })
+        }
+
+        fn into_owned(self) -> Consume {
+            Consume {
+                cid: self.cid,
+                id: self.id,
+                ticket: self.ticket,
+                queue: self.queue.to_string(),
+                consumer_tag: self.consumer_tag.to_string(),
+                no_local: self.no_local,
+                no_ack: self.no_ack,
+                exclusive: self.exclusive,
+                nowait: self.nowait,
+                arguments: self.arguments,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct ConsumeOk {
         cid: Short,
         id: Short,
@@ -1278,11 +3168,25 @@ pub mod basic {
     }
 
     impl Method for ConsumeOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "consumer_tag" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            try!(writer.write_short_str(&self.consumer_tag));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<ConsumeOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let consumer_tag = try!(reader.read_short_str("consumer_tag"));
+            try!(reader.finish());
+            Ok(ConsumeOk {
+                cid: cid,
+                id: id,
+                consumer_tag: consumer_tag,
+            })
         }
     }
 
@@ -1296,7 +3200,39 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct ConsumeOkRef<'a> {
+        cid: Short,
+        id: Short,
+        pub consumer_tag: &'a str,
+    }
+
+    impl<'a> MethodRef<'a> for ConsumeOkRef<'a> {
+        type Owned = ConsumeOk;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<ConsumeOkRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let consumer_tag = try!(reader.read_short_str_ref("consumer_tag"));
+            try!(reader.finish());
+            Ok(ConsumeOkRef {
+                cid: cid,
+                id: id,
+                consumer_tag: consumer_tag,
+            })
+        }
+
+        fn into_owned(self) -> ConsumeOk {
+            ConsumeOk {
+                cid: self.cid,
+                id: self.id,
+                consumer_tag: self.consumer_tag.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct Cancel {
         cid: Short,
         id: Short,
@@ -1305,11 +3241,29 @@ pub mod basic {
     }
 
     impl Method for Cancel {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "consumer_tag" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            try!(writer.write_short_str(&self.consumer_tag));
+            writer.write_bits(&[self.nowait]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Cancel> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let consumer_tag = try!(reader.read_short_str("consumer_tag"));
+            let bits = try!(reader.read_octet("nowait"));
+            let nowait = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(Cancel {
+                cid: cid,
+                id: id,
+                consumer_tag: consumer_tag,
+                nowait: nowait,
+            })
         }
     }
 
@@ -1324,7 +3278,44 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct CancelRef<'a> {
+        cid: Short,
+        id: Short,
+        pub consumer_tag: &'a str,
+        pub nowait: Bit,
+    }
+
+    impl<'a> MethodRef<'a> for CancelRef<'a> {
+        type Owned = Cancel;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<CancelRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let consumer_tag = try!(reader.read_short_str_ref("consumer_tag"));
+            let bits = try!(reader.read_octet("nowait"));
+            let nowait = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(CancelRef {
+                cid: cid,
+                id: id,
+                consumer_tag: consumer_tag,
+                nowait: nowait,
+            })
+        }
+
+        fn into_owned(self) -> Cancel {
+            Cancel {
+                cid: self.cid,
+                id: self.id,
+                consumer_tag: self.consumer_tag.to_string(),
+                nowait: self.nowait,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct CancelOk {
         cid: Short,
         id: Short,
@@ -1332,11 +3323,25 @@ pub mod basic {
     }
 
     impl Method for CancelOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "consumer_tag" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            try!(writer.write_short_str(&self.consumer_tag));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<CancelOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let consumer_tag = try!(reader.read_short_str("consumer_tag"));
+            try!(reader.finish());
+            Ok(CancelOk {
+                cid: cid,
+                id: id,
+                consumer_tag: consumer_tag,
+            })
         }
     }
 
@@ -1350,7 +3355,39 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct CancelOkRef<'a> {
+        cid: Short,
+        id: Short,
+        pub consumer_tag: &'a str,
+    }
+
+    impl<'a> MethodRef<'a> for CancelOkRef<'a> {
+        type Owned = CancelOk;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<CancelOkRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let consumer_tag = try!(reader.read_short_str_ref("consumer_tag"));
+            try!(reader.finish());
+            Ok(CancelOkRef {
+                cid: cid,
+                id: id,
+                consumer_tag: consumer_tag,
+            })
+        }
+
+        fn into_owned(self) -> CancelOk {
+            CancelOk {
+                cid: self.cid,
+                id: self.id,
+                consumer_tag: self.consumer_tag.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct Publish {
         cid: Short,
         id: Short,
@@ -1362,12 +3399,37 @@ pub mod basic {
     }
 
     impl Method for Publish {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "exchange" => Some(StrType::Short),
-                "routing_key" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            try!(writer.write_short_str(&self.exchange));
+            try!(writer.write_short_str(&self.routing_key));
+            writer.write_bits(&[self.mandatory, self.immediate]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Publish> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let exchange = try!(reader.read_short_str("exchange"));
+            let routing_key = try!(reader.read_short_str("routing_key"));
+            let bits = try!(reader.read_octet("mandatory"));
+            let mandatory = bits & 0x01 != 0;
+            let immediate = bits & 0x02 != 0;
+            try!(reader.finish());
+            Ok(Publish {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                exchange: exchange,
+                routing_key: routing_key,
+                mandatory: mandatory,
+                immediate: immediate,
+            })
         }
     }
 
@@ -1385,7 +3447,56 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct PublishRef<'a> {
+        cid: Short,
+        id: Short,
+        pub ticket: Short,
+        pub exchange: &'a str,
+        pub routing_key: &'a str,
+        pub mandatory: Bit,
+        pub immediate: Bit,
+    }
+
+    impl<'a> MethodRef<'a> for PublishRef<'a> {
+        type Owned = Publish;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<PublishRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let exchange = try!(reader.read_short_str_ref("exchange"));
+            let routing_key = try!(reader.read_short_str_ref("routing_key"));
+            let bits = try!(reader.read_octet("mandatory"));
+            let mandatory = bits & 0x01 != 0;
+            let immediate = bits & 0x02 != 0;
+            try!(reader.finish());
+            Ok(PublishRef {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                exchange: exchange,
+                routing_key: routing_key,
+                mandatory: mandatory,
+                immediate: immediate,
+            })
+        }
+
+        fn into_owned(self) -> Publish {
+            Publish {
+                cid: self.cid,
+                id: self.id,
+                ticket: self.ticket,
+                exchange: self.exchange.to_string(),
+                routing_key: self.routing_key.to_string(),
+                mandatory: self.mandatory,
+                immediate: self.immediate,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct Return {
         cid: Short,
         id: Short,
@@ -1396,13 +3507,34 @@ pub mod basic {
     }
 
     impl Method for Return {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "reply_text" => Some(StrType::Short),
-                "exchange" => Some(StrType::Short),
-                "routing_key" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.reply_code);
+            try!(writer.write_short_str(&self.reply_text));
+            try!(writer.write_short_str(&self.exchange));
+            try!(writer.write_short_str(&self.routing_key));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Return> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let reply_code = try!(reader.read_short("reply_code"));
+            let reply_text = try!(reader.read_short_str("reply_text"));
+            let exchange = try!(reader.read_short_str("exchange"));
+            let routing_key = try!(reader.read_short_str("routing_key"));
+            try!(reader.finish());
+            Ok(Return {
+                cid: cid,
+                id: id,
+                reply_code: reply_code,
+                reply_text: reply_text,
+                exchange: exchange,
+                routing_key: routing_key,
+            })
         }
     }
 
@@ -1419,7 +3551,51 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct ReturnRef<'a> {
+        cid: Short,
+        id: Short,
+        pub reply_code: Short,
+        pub reply_text: &'a str,
+        pub exchange: &'a str,
+        pub routing_key: &'a str,
+    }
+
+    impl<'a> MethodRef<'a> for ReturnRef<'a> {
+        type Owned = Return;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<ReturnRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let reply_code = try!(reader.read_short("reply_code"));
+            let reply_text = try!(reader.read_short_str_ref("reply_text"));
+            let exchange = try!(reader.read_short_str_ref("exchange"));
+            let routing_key = try!(reader.read_short_str_ref("routing_key"));
+            try!(reader.finish());
+            Ok(ReturnRef {
+                cid: cid,
+                id: id,
+                reply_code: reply_code,
+                reply_text: reply_text,
+                exchange: exchange,
+                routing_key: routing_key,
+            })
+        }
+
+        fn into_owned(self) -> Return {
+            Return {
+                cid: self.cid,
+                id: self.id,
+                reply_code: self.reply_code,
+                reply_text: self.reply_text.to_string(),
+                exchange: self.exchange.to_string(),
+                routing_key: self.routing_key.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct Deliver {
         cid: Short,
         id: Short,
@@ -1431,13 +3607,38 @@ pub mod basic {
     }
 
     impl Method for Deliver {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "consumer_tag" => Some(StrType::Short),
-                "exchange" => Some(StrType::Short),
-                "routing_key" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            try!(writer.write_short_str(&self.consumer_tag));
+            writer.write_longlong(self.delivery_tag);
+            writer.write_bits(&[self.redelivered]);
+            try!(writer.write_short_str(&self.exchange));
+            try!(writer.write_short_str(&self.routing_key));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Deliver> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let consumer_tag = try!(reader.read_short_str("consumer_tag"));
+            let delivery_tag = try!(reader.read_longlong("delivery_tag"));
+            let bits = try!(reader.read_octet("redelivered"));
+            let redelivered = bits & 0x01 != 0;
+            let exchange = try!(reader.read_short_str("exchange"));
+            let routing_key = try!(reader.read_short_str("routing_key"));
+            try!(reader.finish());
+            Ok(Deliver {
+                cid: cid,
+                id: id,
+                consumer_tag: consumer_tag,
+                delivery_tag: delivery_tag,
+                redelivered: redelivered,
+                exchange: exchange,
+                routing_key: routing_key,
+            })
         }
     }
 
@@ -1455,7 +3656,56 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct DeliverRef<'a> {
+        cid: Short,
+        id: Short,
+        pub consumer_tag: &'a str,
+        pub delivery_tag: Longlong,
+        pub redelivered: Bit,
+        pub exchange: &'a str,
+        pub routing_key: &'a str,
+    }
+
+    impl<'a> MethodRef<'a> for DeliverRef<'a> {
+        type Owned = Deliver;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<DeliverRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let consumer_tag = try!(reader.read_short_str_ref("consumer_tag"));
+            let delivery_tag = try!(reader.read_longlong("delivery_tag"));
+            let bits = try!(reader.read_octet("redelivered"));
+            let redelivered = bits & 0x01 != 0;
+            let exchange = try!(reader.read_short_str_ref("exchange"));
+            let routing_key = try!(reader.read_short_str_ref("routing_key"));
+            try!(reader.finish());
+            Ok(DeliverRef {
+                cid: cid,
+                id: id,
+                consumer_tag: consumer_tag,
+                delivery_tag: delivery_tag,
+                redelivered: redelivered,
+                exchange: exchange,
+                routing_key: routing_key,
+            })
+        }
+
+        fn into_owned(self) -> Deliver {
+            Deliver {
+                cid: self.cid,
+                id: self.id,
+                consumer_tag: self.consumer_tag.to_string(),
+                delivery_tag: self.delivery_tag,
+                redelivered: self.redelivered,
+                exchange: self.exchange.to_string(),
+                routing_key: self.routing_key.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct Get {
         cid: Short,
         id: Short,
@@ -1465,11 +3715,32 @@ pub mod basic {
     }
 
     impl Method for Get {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "queue" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_short(self.ticket);
+            try!(writer.write_short_str(&self.queue));
+            writer.write_bits(&[self.no_ack]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Get> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str("queue"));
+            let bits = try!(reader.read_octet("no_ack"));
+            let no_ack = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(Get {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                no_ack: no_ack,
+            })
         }
     }
 
@@ -1485,7 +3756,48 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct GetRef<'a> {
+        cid: Short,
+        id: Short,
+        pub ticket: Short,
+        pub queue: &'a str,
+        pub no_ack: Bit,
+    }
+
+    impl<'a> MethodRef<'a> for GetRef<'a> {
+        type Owned = Get;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<GetRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let ticket = try!(reader.read_short("ticket"));
+            let queue = try!(reader.read_short_str_ref("queue"));
+            let bits = try!(reader.read_octet("no_ack"));
+            let no_ack = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(GetRef {
+                cid: cid,
+                id: id,
+                ticket: ticket,
+                queue: queue,
+                no_ack: no_ack,
+            })
+        }
+
+        fn into_owned(self) -> Get {
+            Get {
+                cid: self.cid,
+                id: self.id,
+                ticket: self.ticket,
+                queue: self.queue.to_string(),
+                no_ack: self.no_ack,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct GetOk {
         cid: Short,
         id: Short,
@@ -1497,12 +3809,38 @@ pub mod basic {
     }
 
     impl Method for GetOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "exchange" => Some(StrType::Short),
-                "routing_key" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_longlong(self.delivery_tag);
+            writer.write_bits(&[self.redelivered]);
+            try!(writer.write_short_str(&self.exchange));
+            try!(writer.write_short_str(&self.routing_key));
+            writer.write_long(self.message_count);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<GetOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let delivery_tag = try!(reader.read_longlong("delivery_tag"));
+            let bits = try!(reader.read_octet("redelivered"));
+            let redelivered = bits & 0x01 != 0;
+            let exchange = try!(reader.read_short_str("exchange"));
+            let routing_key = try!(reader.read_short_str("routing_key"));
+            let message_count = try!(reader.read_long("message_count"));
+            try!(reader.finish());
+            Ok(GetOk {
+                cid: cid,
+                id: id,
+                delivery_tag: delivery_tag,
+                redelivered: redelivered,
+                exchange: exchange,
+                routing_key: routing_key,
+                message_count: message_count,
+            })
         }
     }
 
@@ -1520,7 +3858,56 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct GetOkRef<'a> {
+        cid: Short,
+        id: Short,
+        pub delivery_tag: Longlong,
+        pub redelivered: Bit,
+        pub exchange: &'a str,
+        pub routing_key: &'a str,
+        pub message_count: Long,
+    }
+
+    impl<'a> MethodRef<'a> for GetOkRef<'a> {
+        type Owned = GetOk;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<GetOkRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let delivery_tag = try!(reader.read_longlong("delivery_tag"));
+            let bits = try!(reader.read_octet("redelivered"));
+            let redelivered = bits & 0x01 != 0;
+            let exchange = try!(reader.read_short_str_ref("exchange"));
+            let routing_key = try!(reader.read_short_str_ref("routing_key"));
+            let message_count = try!(reader.read_long("message_count"));
+            try!(reader.finish());
+            Ok(GetOkRef {
+                cid: cid,
+                id: id,
+                delivery_tag: delivery_tag,
+                redelivered: redelivered,
+                exchange: exchange,
+                routing_key: routing_key,
+                message_count: message_count,
+            })
+        }
+
+        fn into_owned(self) -> GetOk {
+            GetOk {
+                cid: self.cid,
+                id: self.id,
+                delivery_tag: self.delivery_tag,
+                redelivered: self.redelivered,
+                exchange: self.exchange.to_string(),
+                routing_key: self.routing_key.to_string(),
+                message_count: self.message_count,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct GetEmpty {
         cid: Short,
         id: Short,
@@ -1528,11 +3915,25 @@ pub mod basic {
     }
 
     impl Method for GetEmpty {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                "cluster_id" => Some(StrType::Short),
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            try!(writer.write_short_str(&self.cluster_id));
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<GetEmpty> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let cluster_id = try!(reader.read_short_str("cluster_id"));
+            try!(reader.finish());
+            Ok(GetEmpty {
+                cid: cid,
+                id: id,
+                cluster_id: cluster_id,
+            })
         }
     }
 
@@ -1546,7 +3947,39 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
+    pub struct GetEmptyRef<'a> {
+        cid: Short,
+        id: Short,
+        pub cluster_id: &'a str,
+    }
+
+    impl<'a> MethodRef<'a> for GetEmptyRef<'a> {
+        type Owned = GetEmpty;
+
+        fn decode(data: &'a [u8]) -> AmqpResult<GetEmptyRef<'a>> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let cluster_id = try!(reader.read_short_str_ref("cluster_id"));
+            try!(reader.finish());
+            Ok(GetEmptyRef {
+                cid: cid,
+                id: id,
+                cluster_id: cluster_id,
+            })
+        }
+
+        fn into_owned(self) -> GetEmpty {
+            GetEmpty {
+                cid: self.cid,
+                id: self.id,
+                cluster_id: self.cluster_id.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
     pub struct Ack {
         cid: Short,
         id: Short,
@@ -1555,10 +3988,29 @@ pub mod basic {
     }
 
     impl Method for Ack {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_longlong(self.delivery_tag);
+            writer.write_bits(&[self.multiple]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Ack> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let delivery_tag = try!(reader.read_longlong("delivery_tag"));
+            let bits = try!(reader.read_octet("multiple"));
+            let multiple = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(Ack {
+                cid: cid,
+                id: id,
+                delivery_tag: delivery_tag,
+                multiple: multiple,
+            })
         }
     }
 
@@ -1573,7 +4025,7 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Reject {
         cid: Short,
         id: Short,
@@ -1582,10 +4034,29 @@ pub mod basic {
     }
 
     impl Method for Reject {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_longlong(self.delivery_tag);
+            writer.write_bits(&[self.requeue]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Reject> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let delivery_tag = try!(reader.read_longlong("delivery_tag"));
+            let bits = try!(reader.read_octet("requeue"));
+            let requeue = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(Reject {
+                cid: cid,
+                id: id,
+                delivery_tag: delivery_tag,
+                requeue: requeue,
+            })
         }
     }
 
@@ -1600,7 +4071,7 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct RecoverAsync {
         cid: Short,
         id: Short,
@@ -1608,10 +4079,26 @@ pub mod basic {
     }
 
     impl Method for RecoverAsync {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_bits(&[self.requeue]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<RecoverAsync> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let bits = try!(reader.read_octet("requeue"));
+            let requeue = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(RecoverAsync {
+                cid: cid,
+                id: id,
+                requeue: requeue,
+            })
         }
     }
 
@@ -1625,7 +4112,7 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Recover {
         cid: Short,
         id: Short,
@@ -1633,10 +4120,26 @@ pub mod basic {
     }
 
     impl Method for Recover {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_bits(&[self.requeue]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Recover> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let bits = try!(reader.read_octet("requeue"));
+            let requeue = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(Recover {
+                cid: cid,
+                id: id,
+                requeue: requeue,
+            })
         }
     }
 
@@ -1650,17 +4153,29 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct RecoverOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for RecoverOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<RecoverOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(RecoverOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -1673,7 +4188,7 @@ pub mod basic {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Nack {
         cid: Short,
         id: Short,
@@ -1683,10 +4198,31 @@ pub mod basic {
     }
 
     impl Method for Nack {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_longlong(self.delivery_tag);
+            writer.write_bits(&[self.multiple, self.requeue]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Nack> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let delivery_tag = try!(reader.read_longlong("delivery_tag"));
+            let bits = try!(reader.read_octet("multiple"));
+            let multiple = bits & 0x01 != 0;
+            let requeue = bits & 0x02 != 0;
+            try!(reader.finish());
+            Ok(Nack {
+                cid: cid,
+                id: id,
+                delivery_tag: delivery_tag,
+                multiple: multiple,
+                requeue: requeue,
+            })
         }
     }
 
@@ -1704,20 +4240,33 @@ pub mod basic {
 }
 
 pub mod tx {
-    use method::Method;
+    use method::{Method, Reader, Writer};
+    use result::*;
     use types::*;
     
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Select {
         cid: Short,
         id: Short,
     }
 
     impl Method for Select {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Select> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(Select {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -1730,17 +4279,29 @@ pub mod tx {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct SelectOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for SelectOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<SelectOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(SelectOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -1753,17 +4314,29 @@ pub mod tx {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Commit {
         cid: Short,
         id: Short,
     }
 
     impl Method for Commit {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Commit> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(Commit {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -1776,17 +4349,29 @@ pub mod tx {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct CommitOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for CommitOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<CommitOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(CommitOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -1799,17 +4384,29 @@ pub mod tx {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Rollback {
         cid: Short,
         id: Short,
     }
 
     impl Method for Rollback {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Rollback> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(Rollback {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -1822,17 +4419,29 @@ pub mod tx {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct RollbackOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for RollbackOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<RollbackOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(RollbackOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -1847,10 +4456,11 @@ pub mod tx {
 }
 
 pub mod confirm {
-    use method::Method;
+    use method::{Method, Reader, Writer};
+    use result::*;
     use types::*;
     
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct Select {
         cid: Short,
         id: Short,
@@ -1858,10 +4468,26 @@ pub mod confirm {
     }
 
     impl Method for Select {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            writer.write_bits(&[self.nowait]);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<Select> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            let bits = try!(reader.read_octet("nowait"));
+            let nowait = bits & 0x01 != 0;
+            try!(reader.finish());
+            Ok(Select {
+                cid: cid,
+                id: id,
+                nowait: nowait,
+            })
         }
     }
 
@@ -1875,17 +4501,29 @@ pub mod confirm {
         }
     }
 
-    #[derive(Debug, PartialEq, RustcEncodable, RustcDecodable)]
+    #[derive(Debug, PartialEq)]
     pub struct SelectOk {
         cid: Short,
         id: Short,
     }
 
     impl Method for SelectOk {
-        fn str_type(param: &str) -> Option<StrType> {
-            match param { 
-                _ => None
-            }
+        fn encode_into(&self, buf: &mut Vec<u8>) -> AmqpResult<()> {
+            let mut writer = Writer::new(buf);
+            writer.write_short(self.cid);
+            writer.write_short(self.id);
+            Ok(())
+        }
+
+        fn decode(data: &[u8]) -> AmqpResult<SelectOk> {
+            let mut reader = Reader::new(data);
+            let cid = try!(reader.read_short("class_id"));
+            let id = try!(reader.read_short("method_id"));
+            try!(reader.finish());
+            Ok(SelectOk {
+                cid: cid,
+                id: id,
+            })
         }
     }
 
@@ -1898,3 +4536,354 @@ pub mod confirm {
         }
     }
 }
+
+#[derive(Debug, PartialEq)]
+pub enum AmqpMethod {
+    ConnectionStart(connection::Start),
+    ConnectionStartOk(connection::StartOk),
+    ConnectionSecure(connection::Secure),
+    ConnectionSecureOk(connection::SecureOk),
+    ConnectionTune(connection::Tune),
+    ConnectionTuneOk(connection::TuneOk),
+    ConnectionOpen(connection::Open),
+    ConnectionOpenOk(connection::OpenOk),
+    ConnectionClose(connection::Close),
+    ConnectionCloseOk(connection::CloseOk),
+    ConnectionBlocked(connection::Blocked),
+    ConnectionUnblocked(connection::Unblocked),
+    ChannelOpen(channel::Open),
+    ChannelOpenOk(channel::OpenOk),
+    ChannelFlow(channel::Flow),
+    ChannelFlowOk(channel::FlowOk),
+    ChannelClose(channel::Close),
+    ChannelCloseOk(channel::CloseOk),
+    AccessRequest(access::Request),
+    AccessRequestOk(access::RequestOk),
+    ExchangeDeclare(exchange::Declare),
+    ExchangeDeclareOk(exchange::DeclareOk),
+    ExchangeDelete(exchange::Delete),
+    ExchangeDeleteOk(exchange::DeleteOk),
+    ExchangeBind(exchange::Bind),
+    ExchangeBindOk(exchange::BindOk),
+    ExchangeUnbind(exchange::Unbind),
+    ExchangeUnbindOk(exchange::UnbindOk),
+    QueueDeclare(queue::Declare),
+    QueueDeclareOk(queue::DeclareOk),
+    QueueBind(queue::Bind),
+    QueueBindOk(queue::BindOk),
+    QueuePurge(queue::Purge),
+    QueuePurgeOk(queue::PurgeOk),
+    QueueDelete(queue::Delete),
+    QueueDeleteOk(queue::DeleteOk),
+    QueueUnbind(queue::Unbind),
+    QueueUnbindOk(queue::UnbindOk),
+    BasicQos(basic::Qos),
+    BasicQosOk(basic::QosOk),
+    BasicConsume(basic::Consume),
+    BasicConsumeOk(basic::ConsumeOk),
+    BasicCancel(basic::Cancel),
+    BasicCancelOk(basic::CancelOk),
+    BasicPublish(basic::Publish),
+    BasicReturn(basic::Return),
+    BasicDeliver(basic::Deliver),
+    BasicGet(basic::Get),
+    BasicGetOk(basic::GetOk),
+    BasicGetEmpty(basic::GetEmpty),
+    BasicAck(basic::Ack),
+    BasicReject(basic::Reject),
+    BasicRecoverAsync(basic::RecoverAsync),
+    BasicRecover(basic::Recover),
+    BasicRecoverOk(basic::RecoverOk),
+    BasicNack(basic::Nack),
+    TxSelect(tx::Select),
+    TxSelectOk(tx::SelectOk),
+    TxCommit(tx::Commit),
+    TxCommitOk(tx::CommitOk),
+    TxRollback(tx::Rollback),
+    TxRollbackOk(tx::RollbackOk),
+    ConfirmSelect(confirm::Select),
+    ConfirmSelectOk(confirm::SelectOk),
+}
+
+impl AmqpMethod {
+    pub fn decode(payload: &[u8]) -> AmqpResult<AmqpMethod> {
+        let mut ids = payload;
+        let class_id = try!(ids.read_u16::<BigEndian>());
+        let method_id = try!(ids.read_u16::<BigEndian>());
+        let method = match (class_id, method_id) {
+            (10, 10) => AmqpMethod::ConnectionStart(try!(method::de(payload))),
+            (10, 11) => AmqpMethod::ConnectionStartOk(try!(method::de(payload))),
+            (10, 20) => AmqpMethod::ConnectionSecure(try!(method::de(payload))),
+            (10, 21) => AmqpMethod::ConnectionSecureOk(try!(method::de(payload))),
+            (10, 30) => AmqpMethod::ConnectionTune(try!(method::de(payload))),
+            (10, 31) => AmqpMethod::ConnectionTuneOk(try!(method::de(payload))),
+            (10, 40) => AmqpMethod::ConnectionOpen(try!(method::de(payload))),
+            (10, 41) => AmqpMethod::ConnectionOpenOk(try!(method::de(payload))),
+            (10, 50) => AmqpMethod::ConnectionClose(try!(method::de(payload))),
+            (10, 51) => AmqpMethod::ConnectionCloseOk(try!(method::de(payload))),
+            (10, 60) => AmqpMethod::ConnectionBlocked(try!(method::de(payload))),
+            (10, 61) => AmqpMethod::ConnectionUnblocked(try!(method::de(payload))),
+            (20, 10) => AmqpMethod::ChannelOpen(try!(method::de(payload))),
+            (20, 11) => AmqpMethod::ChannelOpenOk(try!(method::de(payload))),
+            (20, 20) => AmqpMethod::ChannelFlow(try!(method::de(payload))),
+            (20, 21) => AmqpMethod::ChannelFlowOk(try!(method::de(payload))),
+            (20, 40) => AmqpMethod::ChannelClose(try!(method::de(payload))),
+            (20, 41) => AmqpMethod::ChannelCloseOk(try!(method::de(payload))),
+            (30, 10) => AmqpMethod::AccessRequest(try!(method::de(payload))),
+            (30, 11) => AmqpMethod::AccessRequestOk(try!(method::de(payload))),
+            (40, 10) => AmqpMethod::ExchangeDeclare(try!(method::de(payload))),
+            (40, 11) => AmqpMethod::ExchangeDeclareOk(try!(method::de(payload))),
+            (40, 20) => AmqpMethod::ExchangeDelete(try!(method::de(payload))),
+            (40, 21) => AmqpMethod::ExchangeDeleteOk(try!(method::de(payload))),
+            (40, 30) => AmqpMethod::ExchangeBind(try!(method::de(payload))),
+            (40, 31) => AmqpMethod::ExchangeBindOk(try!(method::de(payload))),
+            (40, 40) => AmqpMethod::ExchangeUnbind(try!(method::de(payload))),
+            (40, 51) => AmqpMethod::ExchangeUnbindOk(try!(method::de(payload))),
+            (50, 10) => AmqpMethod::QueueDeclare(try!(method::de(payload))),
+            (50, 11) => AmqpMethod::QueueDeclareOk(try!(method::de(payload))),
+            (50, 20) => AmqpMethod::QueueBind(try!(method::de(payload))),
+            (50, 21) => AmqpMethod::QueueBindOk(try!(method::de(payload))),
+            (50, 30) => AmqpMethod::QueuePurge(try!(method::de(payload))),
+            (50, 31) => AmqpMethod::QueuePurgeOk(try!(method::de(payload))),
+            (50, 40) => AmqpMethod::QueueDelete(try!(method::de(payload))),
+            (50, 41) => AmqpMethod::QueueDeleteOk(try!(method::de(payload))),
+            (50, 50) => AmqpMethod::QueueUnbind(try!(method::de(payload))),
+            (50, 51) => AmqpMethod::QueueUnbindOk(try!(method::de(payload))),
+            (60, 10) => AmqpMethod::BasicQos(try!(method::de(payload))),
+            (60, 11) => AmqpMethod::BasicQosOk(try!(method::de(payload))),
+            (60, 20) => AmqpMethod::BasicConsume(try!(method::de(payload))),
+            (60, 21) => AmqpMethod::BasicConsumeOk(try!(method::de(payload))),
+            (60, 30) => AmqpMethod::BasicCancel(try!(method::de(payload))),
+            (60, 31) => AmqpMethod::BasicCancelOk(try!(method::de(payload))),
+            (60, 40) => AmqpMethod::BasicPublish(try!(method::de(payload))),
+            (60, 50) => AmqpMethod::BasicReturn(try!(method::de(payload))),
+            (60, 60) => AmqpMethod::BasicDeliver(try!(method::de(payload))),
+            (60, 70) => AmqpMethod::BasicGet(try!(method::de(payload))),
+            (60, 71) => AmqpMethod::BasicGetOk(try!(method::de(payload))),
+            (60, 72) => AmqpMethod::BasicGetEmpty(try!(method::de(payload))),
+            (60, 80) => AmqpMethod::BasicAck(try!(method::de(payload))),
+            (60, 90) => AmqpMethod::BasicReject(try!(method::de(payload))),
+            (60, 100) => AmqpMethod::BasicRecoverAsync(try!(method::de(payload))),
+            (60, 110) => AmqpMethod::BasicRecover(try!(method::de(payload))),
+            (60, 111) => AmqpMethod::BasicRecoverOk(try!(method::de(payload))),
+            (60, 120) => AmqpMethod::BasicNack(try!(method::de(payload))),
+            (90, 10) => AmqpMethod::TxSelect(try!(method::de(payload))),
+            (90, 11) => AmqpMethod::TxSelectOk(try!(method::de(payload))),
+            (90, 20) => AmqpMethod::TxCommit(try!(method::de(payload))),
+            (90, 21) => AmqpMethod::TxCommitOk(try!(method::de(payload))),
+            (90, 30) => AmqpMethod::TxRollback(try!(method::de(payload))),
+            (90, 31) => AmqpMethod::TxRollbackOk(try!(method::de(payload))),
+            (85, 10) => AmqpMethod::ConfirmSelect(try!(method::de(payload))),
+            (85, 11) => AmqpMethod::ConfirmSelectOk(try!(method::de(payload))),
+            _ => return Err(AmqpError::UnknownMethod(class_id, method_id)),
+        };
+        Ok(method)
+    }
+
+    pub fn class_id(&self) -> Short {
+        match *self {
+            AmqpMethod::ConnectionStart(_) => 10,
+            AmqpMethod::ConnectionStartOk(_) => 10,
+            AmqpMethod::ConnectionSecure(_) => 10,
+            AmqpMethod::ConnectionSecureOk(_) => 10,
+            AmqpMethod::ConnectionTune(_) => 10,
+            AmqpMethod::ConnectionTuneOk(_) => 10,
+            AmqpMethod::ConnectionOpen(_) => 10,
+            AmqpMethod::ConnectionOpenOk(_) => 10,
+            AmqpMethod::ConnectionClose(_) => 10,
+            AmqpMethod::ConnectionCloseOk(_) => 10,
+            AmqpMethod::ConnectionBlocked(_) => 10,
+            AmqpMethod::ConnectionUnblocked(_) => 10,
+            AmqpMethod::ChannelOpen(_) => 20,
+            AmqpMethod::ChannelOpenOk(_) => 20,
+            AmqpMethod::ChannelFlow(_) => 20,
+            AmqpMethod::ChannelFlowOk(_) => 20,
+            AmqpMethod::ChannelClose(_) => 20,
+            AmqpMethod::ChannelCloseOk(_) => 20,
+            AmqpMethod::AccessRequest(_) => 30,
+            AmqpMethod::AccessRequestOk(_) => 30,
+            AmqpMethod::ExchangeDeclare(_) => 40,
+            AmqpMethod::ExchangeDeclareOk(_) => 40,
+            AmqpMethod::ExchangeDelete(_) => 40,
+            AmqpMethod::ExchangeDeleteOk(_) => 40,
+            AmqpMethod::ExchangeBind(_) => 40,
+            AmqpMethod::ExchangeBindOk(_) => 40,
+            AmqpMethod::ExchangeUnbind(_) => 40,
+            AmqpMethod::ExchangeUnbindOk(_) => 40,
+            AmqpMethod::QueueDeclare(_) => 50,
+            AmqpMethod::QueueDeclareOk(_) => 50,
+            AmqpMethod::QueueBind(_) => 50,
+            AmqpMethod::QueueBindOk(_) => 50,
+            AmqpMethod::QueuePurge(_) => 50,
+            AmqpMethod::QueuePurgeOk(_) => 50,
+            AmqpMethod::QueueDelete(_) => 50,
+            AmqpMethod::QueueDeleteOk(_) => 50,
+            AmqpMethod::QueueUnbind(_) => 50,
+            AmqpMethod::QueueUnbindOk(_) => 50,
+            AmqpMethod::BasicQos(_) => 60,
+            AmqpMethod::BasicQosOk(_) => 60,
+            AmqpMethod::BasicConsume(_) => 60,
+            AmqpMethod::BasicConsumeOk(_) => 60,
+            AmqpMethod::BasicCancel(_) => 60,
+            AmqpMethod::BasicCancelOk(_) => 60,
+            AmqpMethod::BasicPublish(_) => 60,
+            AmqpMethod::BasicReturn(_) => 60,
+            AmqpMethod::BasicDeliver(_) => 60,
+            AmqpMethod::BasicGet(_) => 60,
+            AmqpMethod::BasicGetOk(_) => 60,
+            AmqpMethod::BasicGetEmpty(_) => 60,
+            AmqpMethod::BasicAck(_) => 60,
+            AmqpMethod::BasicReject(_) => 60,
+            AmqpMethod::BasicRecoverAsync(_) => 60,
+            AmqpMethod::BasicRecover(_) => 60,
+            AmqpMethod::BasicRecoverOk(_) => 60,
+            AmqpMethod::BasicNack(_) => 60,
+            AmqpMethod::TxSelect(_) => 90,
+            AmqpMethod::TxSelectOk(_) => 90,
+            AmqpMethod::TxCommit(_) => 90,
+            AmqpMethod::TxCommitOk(_) => 90,
+            AmqpMethod::TxRollback(_) => 90,
+            AmqpMethod::TxRollbackOk(_) => 90,
+            AmqpMethod::ConfirmSelect(_) => 85,
+            AmqpMethod::ConfirmSelectOk(_) => 85,
+        }
+    }
+
+    pub fn method_id(&self) -> Short {
+        match *self {
+            AmqpMethod::ConnectionStart(_) => 10,
+            AmqpMethod::ConnectionStartOk(_) => 11,
+            AmqpMethod::ConnectionSecure(_) => 20,
+            AmqpMethod::ConnectionSecureOk(_) => 21,
+            AmqpMethod::ConnectionTune(_) => 30,
+            AmqpMethod::ConnectionTuneOk(_) => 31,
+            AmqpMethod::ConnectionOpen(_) => 40,
+            AmqpMethod::ConnectionOpenOk(_) => 41,
+            AmqpMethod::ConnectionClose(_) => 50,
+            AmqpMethod::ConnectionCloseOk(_) => 51,
+            AmqpMethod::ConnectionBlocked(_) => 60,
+            AmqpMethod::ConnectionUnblocked(_) => 61,
+            AmqpMethod::ChannelOpen(_) => 10,
+            AmqpMethod::ChannelOpenOk(_) => 11,
+            AmqpMethod::ChannelFlow(_) => 20,
+            AmqpMethod::ChannelFlowOk(_) => 21,
+            AmqpMethod::ChannelClose(_) => 40,
+            AmqpMethod::ChannelCloseOk(_) => 41,
+            AmqpMethod::AccessRequest(_) => 10,
+            AmqpMethod::AccessRequestOk(_) => 11,
+            AmqpMethod::ExchangeDeclare(_) => 10,
+            AmqpMethod::ExchangeDeclareOk(_) => 11,
+            AmqpMethod::ExchangeDelete(_) => 20,
+            AmqpMethod::ExchangeDeleteOk(_) => 21,
+            AmqpMethod::ExchangeBind(_) => 30,
+            AmqpMethod::ExchangeBindOk(_) => 31,
+            AmqpMethod::ExchangeUnbind(_) => 40,
+            AmqpMethod::ExchangeUnbindOk(_) => 51,
+            AmqpMethod::QueueDeclare(_) => 10,
+            AmqpMethod::QueueDeclareOk(_) => 11,
+            AmqpMethod::QueueBind(_) => 20,
+            AmqpMethod::QueueBindOk(_) => 21,
+            AmqpMethod::QueuePurge(_) => 30,
+            AmqpMethod::QueuePurgeOk(_) => 31,
+            AmqpMethod::QueueDelete(_) => 40,
+            AmqpMethod::QueueDeleteOk(_) => 41,
+            AmqpMethod::QueueUnbind(_) => 50,
+            AmqpMethod::QueueUnbindOk(_) => 51,
+            AmqpMethod::BasicQos(_) => 10,
+            AmqpMethod::BasicQosOk(_) => 11,
+            AmqpMethod::BasicConsume(_) => 20,
+            AmqpMethod::BasicConsumeOk(_) => 21,
+            AmqpMethod::BasicCancel(_) => 30,
+            AmqpMethod::BasicCancelOk(_) => 31,
+            AmqpMethod::BasicPublish(_) => 40,
+            AmqpMethod::BasicReturn(_) => 50,
+            AmqpMethod::BasicDeliver(_) => 60,
+            AmqpMethod::BasicGet(_) => 70,
+            AmqpMethod::BasicGetOk(_) => 71,
+            AmqpMethod::BasicGetEmpty(_) => 72,
+            AmqpMethod::BasicAck(_) => 80,
+            AmqpMethod::BasicReject(_) => 90,
+            AmqpMethod::BasicRecoverAsync(_) => 100,
+            AmqpMethod::BasicRecover(_) => 110,
+            AmqpMethod::BasicRecoverOk(_) => 111,
+            AmqpMethod::BasicNack(_) => 120,
+            AmqpMethod::TxSelect(_) => 10,
+            AmqpMethod::TxSelectOk(_) => 11,
+            AmqpMethod::TxCommit(_) => 20,
+            AmqpMethod::TxCommitOk(_) => 21,
+            AmqpMethod::TxRollback(_) => 30,
+            AmqpMethod::TxRollbackOk(_) => 31,
+            AmqpMethod::ConfirmSelect(_) => 10,
+            AmqpMethod::ConfirmSelectOk(_) => 11,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            AmqpMethod::ConnectionStart(_) => "connection.start",
+            AmqpMethod::ConnectionStartOk(_) => "connection.start-ok",
+            AmqpMethod::ConnectionSecure(_) => "connection.secure",
+            AmqpMethod::ConnectionSecureOk(_) => "connection.secure-ok",
+            AmqpMethod::ConnectionTune(_) => "connection.tune",
+            AmqpMethod::ConnectionTuneOk(_) => "connection.tune-ok",
+            AmqpMethod::ConnectionOpen(_) => "connection.open",
+            AmqpMethod::ConnectionOpenOk(_) => "connection.open-ok",
+            AmqpMethod::ConnectionClose(_) => "connection.close",
+            AmqpMethod::ConnectionCloseOk(_) => "connection.close-ok",
+            AmqpMethod::ConnectionBlocked(_) => "connection.blocked",
+            AmqpMethod::ConnectionUnblocked(_) => "connection.unblocked",
+            AmqpMethod::ChannelOpen(_) => "channel.open",
+            AmqpMethod::ChannelOpenOk(_) => "channel.open-ok",
+            AmqpMethod::ChannelFlow(_) => "channel.flow",
+            AmqpMethod::ChannelFlowOk(_) => "channel.flow-ok",
+            AmqpMethod::ChannelClose(_) => "channel.close",
+            AmqpMethod::ChannelCloseOk(_) => "channel.close-ok",
+            AmqpMethod::AccessRequest(_) => "access.request",
+            AmqpMethod::AccessRequestOk(_) => "access.request-ok",
+            AmqpMethod::ExchangeDeclare(_) => "exchange.declare",
+            AmqpMethod::ExchangeDeclareOk(_) => "exchange.declare-ok",
+            AmqpMethod::ExchangeDelete(_) => "exchange.delete",
+            AmqpMethod::ExchangeDeleteOk(_) => "exchange.delete-ok",
+            AmqpMethod::ExchangeBind(_) => "exchange.bind",
+            AmqpMethod::ExchangeBindOk(_) => "exchange.bind-ok",
+            AmqpMethod::ExchangeUnbind(_) => "exchange.unbind",
+            AmqpMethod::ExchangeUnbindOk(_) => "exchange.unbind-ok",
+            AmqpMethod::QueueDeclare(_) => "queue.declare",
+            AmqpMethod::QueueDeclareOk(_) => "queue.declare-ok",
+            AmqpMethod::QueueBind(_) => "queue.bind",
+            AmqpMethod::QueueBindOk(_) => "queue.bind-ok",
+            AmqpMethod::QueuePurge(_) => "queue.purge",
+            AmqpMethod::QueuePurgeOk(_) => "queue.purge-ok",
+            AmqpMethod::QueueDelete(_) => "queue.delete",
+            AmqpMethod::QueueDeleteOk(_) => "queue.delete-ok",
+            AmqpMethod::QueueUnbind(_) => "queue.unbind",
+            AmqpMethod::QueueUnbindOk(_) => "queue.unbind-ok",
+            AmqpMethod::BasicQos(_) => "basic.qos",
+            AmqpMethod::BasicQosOk(_) => "basic.qos-ok",
+            AmqpMethod::BasicConsume(_) => "basic.consume",
+            AmqpMethod::BasicConsumeOk(_) => "basic.consume-ok",
+            AmqpMethod::BasicCancel(_) => "basic.cancel",
+            AmqpMethod::BasicCancelOk(_) => "basic.cancel-ok",
+            AmqpMethod::BasicPublish(_) => "basic.publish",
+            AmqpMethod::BasicReturn(_) => "basic.return",
+            AmqpMethod::BasicDeliver(_) => "basic.deliver",
+            AmqpMethod::BasicGet(_) => "basic.get",
+            AmqpMethod::BasicGetOk(_) => "basic.get-ok",
+            AmqpMethod::BasicGetEmpty(_) => "basic.get-empty",
+            AmqpMethod::BasicAck(_) => "basic.ack",
+            AmqpMethod::BasicReject(_) => "basic.reject",
+            AmqpMethod::BasicRecoverAsync(_) => "basic.recover-async",
+            AmqpMethod::BasicRecover(_) => "basic.recover",
+            AmqpMethod::BasicRecoverOk(_) => "basic.recover-ok",
+            AmqpMethod::BasicNack(_) => "basic.nack",
+            AmqpMethod::TxSelect(_) => "tx.select",
+            AmqpMethod::TxSelectOk(_) => "tx.select-ok",
+            AmqpMethod::TxCommit(_) => "tx.commit",
+            AmqpMethod::TxCommitOk(_) => "tx.commit-ok",
+            AmqpMethod::TxRollback(_) => "tx.rollback",
+            AmqpMethod::TxRollbackOk(_) => "tx.rollback-ok",
+            AmqpMethod::ConfirmSelect(_) => "confirm.select",
+            AmqpMethod::ConfirmSelectOk(_) => "confirm.select-ok",
+        }
+    }
+}
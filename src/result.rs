@@ -6,12 +6,45 @@ use std::result;
 use std::string;
 use std::u8;
 
+use channel::Mode;
 use protocol;
 
 #[derive(Debug)]
 pub enum AmqpError {
     ShortStrTooLong(usize),
     FrameEndErr(u8),
+    FrameTypeErr(u8, u8),
+    FrameSizeErr(usize, usize),
+    UnknownMethod(u16, u16),
+    UnexpectedFrame {
+        expected: (u16, u16),
+        got: (u16, u16),
+    },
+    SyntaxErr(String),
+    DecodeErr {
+        offset: usize,
+        field: String,
+        reason: String,
+    },
+    ChannelMaxErr(u16),
+    ChannelModeErr(Mode, Mode),
+    FieldTypeErr(&'static str),
+    HeaderErr(String),
+    PropertyErr(String),
+    ArgumentErr(String),
+    Timeout,
+    ConsumerCancelled(String),
+    ConnectionClosed,
+    ContentTypeErr(Option<String>),
+    ContentEncodingErr(String),
+    CodecErr(String),
+    BodySizeErr(usize),
+    ChannelClosed {
+        reply_code: u16,
+        reply_text: String,
+        class_id: u16,
+        method_id: u16,
+    },
     IoErr(io::Error),
     Utf8Err(string::FromUtf8Error),
 }
@@ -33,13 +66,54 @@ impl Error for AmqpError {
         match *self {
             AmqpError::ShortStrTooLong(_) => "short string length is too long",
             AmqpError::FrameEndErr(_) => "frame end error",
+            AmqpError::FrameTypeErr(..) => "frame type error",
+            AmqpError::FrameSizeErr(..) => "frame too large",
+            AmqpError::UnknownMethod(..) => "unknown method",
+            AmqpError::UnexpectedFrame { .. } => "unexpected frame",
+            AmqpError::SyntaxErr(_) => "syntax error",
+            AmqpError::DecodeErr { .. } => "decode error",
+            AmqpError::ChannelMaxErr(_) => "no free channel id",
+            AmqpError::ChannelModeErr(..) => "channel mode conflict",
+            AmqpError::FieldTypeErr(_) => "field value type mismatch",
+            AmqpError::HeaderErr(_) => "invalid header",
+            AmqpError::PropertyErr(_) => "invalid property",
+            AmqpError::ArgumentErr(_) => "invalid argument",
+            AmqpError::Timeout => "timed out",
+            AmqpError::ConsumerCancelled(_) => "consumer cancelled",
+            AmqpError::ConnectionClosed => "connection closed",
+            AmqpError::ContentTypeErr(_) => "unsupported content type",
+            AmqpError::ContentEncodingErr(_) => "unsupported content encoding",
+            AmqpError::CodecErr(_) => "body codec error",
+            AmqpError::BodySizeErr(_) => "body too large",
+            AmqpError::ChannelClosed { .. } => "channel closed",
             _ => Error::description(self),
         }
     }
 
     fn cause(&self) -> Option<&Error> {
         match *self {
-            AmqpError::ShortStrTooLong(_) | AmqpError::FrameEndErr(_) => None,
+            AmqpError::ShortStrTooLong(_) |
+            AmqpError::FrameEndErr(_) |
+            AmqpError::FrameTypeErr(..) |
+            AmqpError::FrameSizeErr(..) |
+            AmqpError::UnknownMethod(..) |
+            AmqpError::UnexpectedFrame { .. } |
+            AmqpError::SyntaxErr(_) |
+            AmqpError::DecodeErr { .. } |
+            AmqpError::ChannelMaxErr(_) |
+            AmqpError::ChannelModeErr(..) |
+            AmqpError::FieldTypeErr(_) |
+            AmqpError::HeaderErr(_) |
+            AmqpError::PropertyErr(_) |
+            AmqpError::ArgumentErr(_) |
+            AmqpError::Timeout |
+            AmqpError::ConsumerCancelled(_) |
+            AmqpError::ConnectionClosed |
+            AmqpError::ContentTypeErr(_) |
+            AmqpError::ContentEncodingErr(_) |
+            AmqpError::CodecErr(_) |
+            AmqpError::BodySizeErr(_) |
+            AmqpError::ChannelClosed { .. } => None,
             _ => Some(self as &Error),
         }
     }
@@ -54,6 +128,65 @@ impl fmt::Display for AmqpError {
             AmqpError::FrameEndErr(end) => {
                 write!(f, "frame end[{}] != [{}]", end, protocol::FRAME_END)
             },
+            AmqpError::FrameTypeErr(ty, expected) => {
+                write!(f, "frame type[{}] != [{}]", ty, expected)
+            },
+            AmqpError::FrameSizeErr(size, max) => {
+                write!(f, "frame payload size[{}] > MAX[{}]", size, max)
+            },
+            AmqpError::UnknownMethod(class_id, method_id) => {
+                write!(f, "unknown method class[{}] method[{}]", class_id, method_id)
+            },
+            AmqpError::UnexpectedFrame { expected, got } => {
+                write!(f, "unexpected method class[{}] method[{}], expected class[{}] method[{}]",
+                       got.0, got.1, expected.0, expected.1)
+            },
+            AmqpError::SyntaxErr(ref err) => {
+                write!(f, "syntax error[{}]", err)
+            },
+            AmqpError::DecodeErr { offset, ref field, ref reason } => {
+                write!(f, "decode error at offset[{}] field[{}]: {}", offset, field, reason)
+            },
+            AmqpError::ChannelMaxErr(max) => {
+                write!(f, "channel id > MAX[{}]", max)
+            },
+            AmqpError::ChannelModeErr(current, requested) => {
+                write!(f, "channel mode[{:?}] conflicts with [{:?}]", current, requested)
+            },
+            AmqpError::FieldTypeErr(ty) => {
+                write!(f, "field value is not [{}]", ty)
+            },
+            AmqpError::HeaderErr(ref header) => {
+                write!(f, "invalid header[{}]", header)
+            },
+            AmqpError::PropertyErr(ref property) => {
+                write!(f, "invalid property[{}]", property)
+            },
+            AmqpError::ArgumentErr(ref argument) => {
+                write!(f, "invalid argument[{}]", argument)
+            },
+            AmqpError::ConsumerCancelled(ref consumer_tag) => {
+                write!(f, "consumer[{}] cancelled", consumer_tag)
+            },
+            AmqpError::ContentTypeErr(Some(ref ty)) => {
+                write!(f, "unsupported content type[{}]", ty)
+            },
+            AmqpError::ContentTypeErr(None) => {
+                write!(f, "missing content type")
+            },
+            AmqpError::ContentEncodingErr(ref encoding) => {
+                write!(f, "unsupported content encoding[{}]", encoding)
+            },
+            AmqpError::CodecErr(ref err) => {
+                write!(f, "body codec error[{}]", err)
+            },
+            AmqpError::BodySizeErr(max) => {
+                write!(f, "body size > MAX[{}]", max)
+            },
+            AmqpError::ChannelClosed { reply_code, ref reply_text, class_id, method_id } => {
+                write!(f, "channel closed[{}] {}, class[{}] method[{}]",
+                       reply_code, reply_text, class_id, method_id)
+            },
             _ => write!(f, "{}", Error::description(self)),
         }
     }
@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use arguments::QueueArguments;
+use channel::Channel;
+use consumer::Delivery;
+use death::DeathReason;
+use protocol::{exchange, queue};
+use result::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetryOutcome {
+    Retried(usize),
+    Parked,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub queue: String,
+    pub delays: Vec<Duration>,
+}
+
+impl RetryPolicy {
+    pub fn new(queue: &str, delays: Vec<Duration>) -> RetryPolicy {
+        RetryPolicy {
+            queue: queue.to_string(),
+            delays: delays,
+        }
+    }
+
+    pub fn exchange(&self) -> String {
+        format!("{}.retry", self.queue)
+    }
+
+    pub fn tier_queue(&self, tier: usize) -> String {
+        format!("{}.retry.{}", self.queue, tier)
+    }
+
+    pub fn parking_lot(&self) -> String {
+        format!("{}.parking-lot", self.queue)
+    }
+
+    pub fn declare(&self, channel: &mut Channel) -> AmqpResult<()> {
+        let mut declare = exchange::Declare::default();
+        declare.exchange = self.exchange();
+        declare.durable = true;
+        try!(channel.exchange_declare(&declare));
+
+        for (tier, delay) in self.delays.iter().enumerate() {
+            let queue = self.tier_queue(tier);
+            let arguments = QueueArguments::new()
+                .message_ttl(try!(millis(delay)))
+                .dead_letter_exchange("")
+                .dead_letter_routing_key(&self.queue);
+            try!(self.declare_bound_queue(channel, &queue, arguments));
+        }
+
+        let parking_lot = self.parking_lot();
+        self.declare_bound_queue(channel, &parking_lot, QueueArguments::new())
+    }
+
+    pub fn attempts(&self, delivery: &Delivery) -> AmqpResult<u64> {
+        let history = try!(delivery.death_history());
+        let attempts = history.deaths.iter()
+            .filter(|death| death.reason == DeathReason::Expired)
+            .filter(|death| self.is_tier_queue(&death.queue))
+            .map(|death| death.count)
+            .sum();
+        Ok(attempts)
+    }
+
+    pub fn retry(&self, channel: &mut Channel, delivery: &Delivery) -> AmqpResult<RetryOutcome> {
+        let attempts = try!(self.attempts(delivery)) as usize;
+        let (routing_key, outcome) = if attempts < self.delays.len() {
+            (self.tier_queue(attempts), RetryOutcome::Retried(attempts))
+        } else {
+            (self.parking_lot(), RetryOutcome::Parked)
+        };
+
+        try!(channel.basic_publish(&self.exchange(), &routing_key, &delivery.properties,
+                                   &delivery.body));
+        try!(channel.basic_ack(delivery.delivery_tag, false));
+        Ok(outcome)
+    }
+
+    fn is_tier_queue(&self, queue: &str) -> bool {
+        (0..self.delays.len()).any(|tier| self.tier_queue(tier) == queue)
+    }
+
+    fn declare_bound_queue(&self, channel: &mut Channel, queue: &str, arguments: QueueArguments)
+                           -> AmqpResult<()> {
+        let mut declare = queue::Declare::default();
+        declare.queue = queue.to_string();
+        declare.durable = true;
+        declare.arguments = arguments.into_table();
+        try!(channel.queue_declare(&declare));
+
+        let mut bind = queue::Bind::default();
+        bind.queue = queue.to_string();
+        bind.exchange = self.exchange();
+        bind.routing_key = queue.to_string();
+        channel.queue_bind(&bind)
+    }
+}
+
+fn millis(duration: &Duration) -> AmqpResult<u64> {
+    let millis = duration.as_millis();
+    if millis > i64::MAX as u128 {
+        return Err(AmqpError::ArgumentErr("x-message-ttl".to_string()));
+    }
+    Ok(millis as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use content::BasicProperties;
+    use death::{self, X_DEATH};
+    use types::*;
+    use types::FieldValue::*;
+
+    fn delivery(deaths: Vec<(&str, &str, u64)>) -> Delivery {
+        let deaths = deaths.into_iter()
+            .map(|(queue, reason, count)| death::entry(queue, reason, count))
+            .collect();
+
+        let mut headers = Table::new();
+        headers.insert(X_DEATH.to_string(), FieldArray(deaths));
+        let mut properties = BasicProperties::default();
+        properties.headers = Some(headers);
+        Delivery::for_test(properties, b"")
+    }
+
+    #[test]
+    fn test_names() {
+        let policy = RetryPolicy::new("jobs", vec![Duration::from_secs(5)]);
+        assert_eq!(policy.exchange(), "jobs.retry");
+        assert_eq!(policy.tier_queue(0), "jobs.retry.0");
+        assert_eq!(policy.parking_lot(), "jobs.parking-lot");
+        assert_eq!(millis(&Duration::from_millis(5500)).unwrap(), 5500);
+        assert!(millis(&Duration::from_secs(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_attempts() {
+        let policy = RetryPolicy::new("jobs", vec![Duration::from_secs(5),
+                                                   Duration::from_secs(30),
+                                                   Duration::from_secs(300)]);
+        assert_eq!(policy.attempts(&delivery(vec![])).unwrap(), 0);
+
+        let delivery = delivery(vec![("jobs.retry.1", "expired", 1),
+                                     ("jobs.retry.0", "expired", 1),
+                                     ("jobs", "rejected", 2),
+                                     ("other", "expired", 7)]);
+        assert_eq!(policy.attempts(&delivery).unwrap(), 2);
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use channel::Channel;
+use consumer::{Consumer, ConsumerEvent, Delivery};
+use content::BasicProperties;
+use result::*;
+
+pub const DIRECT_REPLY_TO: &'static str = "amq.rabbitmq.reply-to";
+
+pub struct RpcClient {
+    channel: Channel,
+    id_prefix: String,
+    next_id: u64,
+}
+
+impl RpcClient {
+    pub fn new(mut channel: Channel) -> AmqpResult<RpcClient> {
+        let mut consumer = Consumer::new(DIRECT_REPLY_TO);
+        consumer.no_ack = true;
+        try!(channel.basic_consume(&consumer));
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0));
+        Ok(RpcClient {
+            channel: channel,
+            id_prefix: format!("{:x}{:08x}", now.as_secs(), now.subsec_nanos()),
+            next_id: 0,
+        })
+    }
+
+    pub fn channel(&mut self) -> &mut Channel {
+        &mut self.channel
+    }
+
+    pub fn call(&mut self, exchange: &str, routing_key: &str, props: &BasicProperties,
+                body: &[u8], timeout: Duration) -> AmqpResult<Delivery> {
+        let correlation_id = self.next_correlation_id();
+        let mut props = props.clone();
+        props.reply_to = Some(DIRECT_REPLY_TO.to_string());
+        props.correlation_id = Some(correlation_id.clone());
+        try!(self.channel.basic_publish(exchange, routing_key, &props, body));
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(AmqpError::Timeout);
+            }
+
+            match try!(self.channel.next_event_timeout(deadline - now)) {
+                ConsumerEvent::Delivery(delivery) => {
+                    if delivery.properties.correlation_id.as_ref() == Some(&correlation_id) {
+                        return Ok(delivery);
+                    }
+                },
+                ConsumerEvent::Cancelled(consumer_tag, _) => {
+                    return Err(AmqpError::ConsumerCancelled(consumer_tag));
+                },
+            }
+        }
+    }
+
+    fn next_correlation_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("{}-{}", self.id_prefix, self.next_id)
+    }
+}
+
+pub fn reply(channel: &mut Channel, request: &Delivery, props: &BasicProperties, body: &[u8])
+             -> AmqpResult<()> {
+    let reply_to = match request.properties.reply_to {
+        Some(ref reply_to) => reply_to,
+        None => return Err(AmqpError::PropertyErr("reply_to".to_string())),
+    };
+
+    let mut props = props.clone();
+    props.correlation_id = request.properties.correlation_id.clone();
+    channel.basic_publish("", reply_to, &props, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::basic;
+    use socket::Peer;
+    use std::thread;
+
+    fn client() -> (RpcClient, ::socket::MemorySocket) {
+        let (channel, mut server) = Channel::for_test(4096);
+        let mut consume_ok = basic::ConsumeOk::default();
+        consume_ok.consumer_tag = "amq.ctag-1".to_string();
+        server.write_method(1, &consume_ok);
+        let client = RpcClient::new(channel).unwrap();
+
+        let consume: basic::Consume = server.read_frames()[0].to_method().unwrap();
+        assert_eq!(consume.queue, DIRECT_REPLY_TO);
+        assert!(consume.no_ack);
+        (client, server)
+    }
+
+    fn reply(server: &mut ::socket::MemorySocket, correlation_id: Option<String>, body: &[u8]) {
+        let mut deliver = basic::Deliver::default();
+        deliver.consumer_tag = "amq.ctag-1".to_string();
+        server.write_method(1, &deliver);
+        let mut props = BasicProperties::default();
+        props.correlation_id = correlation_id;
+        server.write_content(1, &props, body);
+    }
+
+    #[test]
+    fn test_call() {
+        let (mut client, mut server) = client();
+        let broker = thread::spawn(move || {
+            let publish: basic::Publish = server.read_frame().to_method().unwrap();
+            assert_eq!(publish.routing_key, "rpc");
+            let header = server.read_frame().to_content_header().unwrap();
+            assert_eq!(server.read_frame().payload, &b"ping"[..]);
+            assert_eq!(header.properties.reply_to, Some(DIRECT_REPLY_TO.to_string()));
+
+            reply(&mut server, Some("stale".to_string()), b"late");
+            reply(&mut server, header.properties.correlation_id, b"pong");
+            server
+        });
+
+        let response = client.call("", "rpc", &BasicProperties::default(), b"ping",
+                                   Duration::from_secs(5)).unwrap();
+        assert_eq!(response.body, &b"pong"[..]);
+        let _server = broker.join().unwrap();
+    }
+
+    #[test]
+    fn test_timeout() {
+        let (mut client, mut server) = client();
+        reply(&mut server, Some("stale".to_string()), b"late");
+
+        let start = Instant::now();
+        match client.call("", "rpc", &BasicProperties::default(), b"ping",
+                          Duration::from_millis(50)) {
+            Err(AmqpError::Timeout) => (),
+            other => panic!("{:?}", other),
+        }
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(server.read_frames().len(), 3);
+    }
+}
@@ -0,0 +1,451 @@
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use std::fmt;
+use std::str;
+use std::{i32, u32, u8};
+
+use types::*;
+use types::FieldValue::*;
+
+impl Serialize for FieldValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Bool(v) => serializer.serialize_bool(v),
+            ShortShortInt(v) => serializer.serialize_i8(v),
+            ShortShortUint(v) => serializer.serialize_u8(v),
+            ShortInt(v) => serializer.serialize_i16(v),
+            ShortUint(v) => serializer.serialize_u16(v),
+            LongInt(v) => serializer.serialize_i32(v),
+            LongUint(v) => serializer.serialize_u32(v),
+            LongLongInt(v) => serializer.serialize_i64(v),
+            LongLongUint(v) => serializer.serialize_u64(v),
+            Float(v) => serializer.serialize_f32(v),
+            Double(v) => serializer.serialize_f64(v),
+            DecimalValue(scale, value) => {
+                let mut map = try!(serializer.serialize_map(Some(2)));
+                try!(map.serialize_entry("scale", &scale));
+                try!(map.serialize_entry("value", &value));
+                map.end()
+            },
+            ShortString(ref v) => serializer.serialize_str(v),
+            LongString(ref v) => serialize_bytes(v, serializer),
+            FieldArray(ref v) => v.serialize(serializer),
+            Timestamp(v) => serializer.serialize_u64(v),
+            FieldTable(ref v) => SortedTableRef(v).serialize(serializer),
+            Void => serializer.serialize_unit(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<FieldValue, D::Error> {
+        deserializer.deserialize_any(FieldValueVisitor)
+    }
+}
+
+pub mod plain {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use types::*;
+    use super::SortedTableRef;
+
+    pub fn serialize<S: Serializer>(table: &Table, serializer: S) -> Result<S::Ok, S::Error> {
+        SortedTableRef(table).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Table, D::Error> {
+        Table::deserialize(deserializer)
+    }
+}
+
+// HashMap iteration order changes from run to run, so tables are written in key order.
+fn sorted(table: &Table) -> Vec<(&FieldName, &FieldValue)> {
+    let mut entries: Vec<_> = table.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+struct SortedTableRef<'a>(&'a Table);
+
+impl<'a> Serialize for SortedTableRef<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = try!(serializer.serialize_map(Some(self.0.len())));
+        for (k, v) in sorted(self.0) {
+            try!(map.serialize_entry(k, v));
+        }
+        map.end()
+    }
+}
+
+fn serialize_bytes<S: Serializer>(v: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    match str::from_utf8(v) {
+        Ok(s) => serializer.serialize_str(s),
+        Err(_) => serializer.collect_seq(v),
+    }
+}
+
+struct FieldValueVisitor;
+
+impl<'de> Visitor<'de> for FieldValueVisitor {
+    type Value = FieldValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an AMQP field value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<FieldValue, E> {
+        Ok(Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<FieldValue, E> {
+        if v >= i32::MIN as i64 && v <= i32::MAX as i64 {
+            Ok(LongInt(v as i32))
+        } else {
+            Ok(LongLongInt(v))
+        }
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<FieldValue, E> {
+        if v <= i32::MAX as u64 {
+            Ok(LongInt(v as i32))
+        } else {
+            Ok(LongLongUint(v))
+        }
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<FieldValue, E> {
+        Ok(Double(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<FieldValue, E> {
+        Ok(LongString(v.as_bytes().to_vec()))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<FieldValue, E> {
+        Ok(LongString(v.to_vec()))
+    }
+
+    fn visit_unit<E>(self) -> Result<FieldValue, E> {
+        Ok(Void)
+    }
+
+    fn visit_none<E>(self) -> Result<FieldValue, E> {
+        Ok(Void)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<FieldValue, D::Error> {
+        FieldValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<FieldValue, A::Error> {
+        let mut array = Vec::new();
+        while let Some(v) = try!(seq.next_element()) {
+            array.push(v);
+        }
+        Ok(FieldArray(array))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FieldValue, A::Error> {
+        let mut table = Table::new();
+        while let Some((k, v)) = try!(map.next_entry()) {
+            table.insert(k, v);
+        }
+        Ok(decimal(&table).unwrap_or(FieldTable(table)))
+    }
+}
+
+// A decimal is written as {"scale", "value"}; a table with exactly those two keys and
+// in-range integers reads back as one.
+fn decimal(table: &Table) -> Option<FieldValue> {
+    if table.len() != 2 {
+        return None;
+    }
+    match (table.get_u64("scale"), table.get_u64("value")) {
+        (Some(scale), Some(value)) if scale <= u8::MAX as u64 && value <= u32::MAX as u64 => {
+            Some(DecimalValue(scale as u8, value as u32))
+        },
+        _ => None,
+    }
+}
+
+pub mod tagged {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use types::*;
+    use super::{TaggedTable, TaggedTableRef};
+
+    pub fn serialize<S: Serializer>(table: &Table, serializer: S) -> Result<S::Ok, S::Error> {
+        TaggedTableRef(table).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Table, D::Error> {
+        TaggedTable::deserialize(deserializer).map(|table| table.0)
+    }
+}
+
+pub struct TaggedRef<'a>(pub &'a FieldValue);
+
+struct TaggedTableRef<'a>(&'a Table);
+
+struct TaggedArrayRef<'a>(&'a [FieldValue]);
+
+impl<'a> Serialize for TaggedRef<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = try!(serializer.serialize_map(Some(1)));
+        match *self.0 {
+            Bool(v) => try!(map.serialize_entry("Bool", &v)),
+            ShortShortInt(v) => try!(map.serialize_entry("ShortShortInt", &v)),
+            ShortShortUint(v) => try!(map.serialize_entry("ShortShortUint", &v)),
+            ShortInt(v) => try!(map.serialize_entry("ShortInt", &v)),
+            ShortUint(v) => try!(map.serialize_entry("ShortUint", &v)),
+            LongInt(v) => try!(map.serialize_entry("LongInt", &v)),
+            LongUint(v) => try!(map.serialize_entry("LongUint", &v)),
+            LongLongInt(v) => try!(map.serialize_entry("LongLongInt", &v)),
+            LongLongUint(v) => try!(map.serialize_entry("LongLongUint", &v)),
+            Float(v) => try!(map.serialize_entry("Float", &v)),
+            Double(v) => try!(map.serialize_entry("Double", &v)),
+            DecimalValue(scale, value) => {
+                try!(map.serialize_entry("DecimalValue", &(scale, value)))
+            },
+            ShortString(ref v) => try!(map.serialize_entry("ShortString", v)),
+            LongString(ref v) => try!(map.serialize_entry("LongString", &LongStringRef(v))),
+            FieldArray(ref v) => try!(map.serialize_entry("FieldArray", &TaggedArrayRef(v))),
+            Timestamp(v) => try!(map.serialize_entry("Timestamp", &v)),
+            FieldTable(ref v) => try!(map.serialize_entry("FieldTable", &TaggedTableRef(v))),
+            Void => try!(map.serialize_entry("Void", &())),
+        }
+        map.end()
+    }
+}
+
+impl<'a> Serialize for TaggedTableRef<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = try!(serializer.serialize_map(Some(self.0.len())));
+        for (k, v) in sorted(self.0) {
+            try!(map.serialize_entry(k, &TaggedRef(v)));
+        }
+        map.end()
+    }
+}
+
+impl<'a> Serialize for TaggedArrayRef<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = try!(serializer.serialize_seq(Some(self.0.len())));
+        for v in self.0 {
+            try!(seq.serialize_element(&TaggedRef(v)));
+        }
+        seq.end()
+    }
+}
+
+struct LongStringRef<'a>(&'a [u8]);
+
+impl<'a> Serialize for LongStringRef<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self.0, serializer)
+    }
+}
+
+pub struct Tagged(pub FieldValue);
+
+struct TaggedTable(Table);
+
+struct TaggedArray(Vec<FieldValue>);
+
+struct LongStringBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for Tagged {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Tagged, D::Error> {
+        deserializer.deserialize_map(TaggedVisitor)
+    }
+}
+
+struct TaggedVisitor;
+
+impl<'de> Visitor<'de> for TaggedVisitor {
+    type Value = Tagged;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a single-entry map of AMQP field type to value")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Tagged, A::Error> {
+        let ty: String = match try!(map.next_key()) {
+            Some(ty) => ty,
+            None => return Err(de::Error::invalid_length(0, &self)),
+        };
+
+        let value = match ty.as_ref() {
+            "Bool" => Bool(try!(map.next_value())),
+            "ShortShortInt" => ShortShortInt(try!(map.next_value())),
+            "ShortShortUint" => ShortShortUint(try!(map.next_value())),
+            "ShortInt" => ShortInt(try!(map.next_value())),
+            "ShortUint" => ShortUint(try!(map.next_value())),
+            "LongInt" => LongInt(try!(map.next_value())),
+            "LongUint" => LongUint(try!(map.next_value())),
+            "LongLongInt" => LongLongInt(try!(map.next_value())),
+            "LongLongUint" => LongLongUint(try!(map.next_value())),
+            "Float" => Float(try!(map.next_value())),
+            "Double" => Double(try!(map.next_value())),
+            "DecimalValue" => {
+                let (scale, value) = try!(map.next_value());
+                DecimalValue(scale, value)
+            },
+            "ShortString" => ShortString(try!(map.next_value())),
+            "LongString" => LongString(try!(map.next_value::<LongStringBuf>()).0),
+            "FieldArray" => FieldArray(try!(map.next_value::<TaggedArray>()).0),
+            "Timestamp" => Timestamp(try!(map.next_value())),
+            "FieldTable" => FieldTable(try!(map.next_value::<TaggedTable>()).0),
+            "Void" => {
+                try!(map.next_value::<()>());
+                Void
+            },
+            other => return Err(de::Error::unknown_variant(other, VARIANTS)),
+        };
+
+        if try!(map.next_key::<String>()).is_some() {
+            return Err(de::Error::invalid_length(2, &self));
+        }
+        Ok(Tagged(value))
+    }
+}
+
+const VARIANTS: &'static [&'static str] = &[
+    "Bool", "ShortShortInt", "ShortShortUint", "ShortInt", "ShortUint", "LongInt", "LongUint",
+    "LongLongInt", "LongLongUint", "Float", "Double", "DecimalValue", "ShortString", "LongString",
+    "FieldArray", "Timestamp", "FieldTable", "Void",
+];
+
+impl<'de> Deserialize<'de> for TaggedTable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<TaggedTable, D::Error> {
+        struct TableVisitor;
+
+        impl<'de> Visitor<'de> for TableVisitor {
+            type Value = TaggedTable;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of tagged AMQP field values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<TaggedTable, A::Error> {
+                let mut table = Table::new();
+                while let Some((k, v)) = try!(map.next_entry::<String, Tagged>()) {
+                    table.insert(k, v.0);
+                }
+                Ok(TaggedTable(table))
+            }
+        }
+
+        deserializer.deserialize_map(TableVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for TaggedArray {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<TaggedArray, D::Error> {
+        let array: Vec<Tagged> = try!(Deserialize::deserialize(deserializer));
+        Ok(TaggedArray(array.into_iter().map(|v| v.0).collect()))
+    }
+}
+
+impl<'de> Deserialize<'de> for LongStringBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<LongStringBuf, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = LongStringBuf;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string or a sequence of bytes")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<LongStringBuf, E> {
+                Ok(LongStringBuf(v.as_bytes().to_vec()))
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<LongStringBuf, E> {
+                Ok(LongStringBuf(v.to_vec()))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<LongStringBuf, A::Error> {
+                let mut bytes = Vec::new();
+                while let Some(b) = try!(seq.next_element()) {
+                    bytes.push(b);
+                }
+                Ok(LongStringBuf(bytes))
+            }
+        }
+
+        deserializer.deserialize_any(BytesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use super::*;
+
+    #[test]
+    fn test_plain() {
+        let table = table!{
+            "e" => table!{ "n" => 1.5f64, "m" => Void },
+            "a" => true,
+            "d" => vec![FieldValue::from(1i32), Void],
+            "c" => "text",
+            "b" => 5u16,
+        };
+
+        let mut json = Vec::new();
+        plain::serialize(&table, &mut serde_json::Serializer::new(&mut json)).unwrap();
+        assert_eq!(str::from_utf8(&json).unwrap(),
+                   r#"{"a":true,"b":5,"c":"text","d":[1,null],"e":{"m":null,"n":1.5}}"#);
+
+        let back = plain::deserialize(&mut serde_json::Deserializer::from_slice(&json)).unwrap();
+        assert_eq!(back["b"], LongInt(5));
+        assert_eq!(back["c"], LongString(b"text".to_vec()));
+        assert_eq!(back["d"], FieldArray(vec![LongInt(1), Void]));
+    }
+
+    #[test]
+    fn test_decimal() {
+        let value = DecimalValue(2, 1234);
+        let json = serde_json::to_value(&value).unwrap();
+        assert_eq!(json, serde_json::json!({ "scale": 2, "value": 1234 }));
+        assert_eq!(serde_json::from_value::<FieldValue>(json).unwrap(), value);
+
+        let json = serde_json::json!({ "scale": 256, "value": 1 });
+        assert_eq!(serde_json::from_value::<FieldValue>(json).unwrap(),
+                   FieldTable(table!{ "scale" => 256i32, "value" => 1i32 }));
+    }
+
+    #[test]
+    fn test_tagged() {
+        let mut table = table!{
+            "a" => 5u16,
+            "b" => -5i64,
+            "c" => vec![0xffu8, 0],
+            "d" => vec![FieldValue::from(1u8), Timestamp(1234)],
+            "e" => table!{ "n" => ShortString("short".to_string()) },
+        };
+        table.insert("f".to_string(), DecimalValue(2, 1234));
+        table.insert("g".to_string(), Void);
+
+        let json = serde_json::to_string(&TaggedTableRef(&table)).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value, serde_json::json!({
+            "a": { "ShortUint": 5 },
+            "b": { "LongLongInt": -5 },
+            "c": { "LongString": [255, 0] },
+            "d": { "FieldArray": [{ "ShortShortUint": 1 }, { "Timestamp": 1234 }] },
+            "e": { "FieldTable": { "n": { "ShortString": "short" } } },
+            "f": { "DecimalValue": [2, 1234] },
+            "g": { "Void": null },
+        }));
+        assert_eq!(json, value.to_string());
+
+        let mut de = serde_json::Deserializer::from_str(&json);
+        let back = tagged::deserialize(&mut de).unwrap();
+        assert_eq!(table, back);
+    }
+}
@@ -1,3 +1,4 @@
+use channel::Channel;
 use conf::Conf;
 use connection::Connection;
 use result::*;
@@ -28,6 +29,10 @@ impl Session {
         self.conn.start()
     }
 
+    pub fn open_channel(&mut self) -> AmqpResult<Channel> {
+        self.conn.open_channel()
+    }
+
     pub fn close(&mut self) -> AmqpResult<()> {
         self.conn.close()
     }
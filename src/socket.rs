@@ -0,0 +1,235 @@
+use std::cell::Cell;
+use std::cmp;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+use byteorder::{BigEndian, ByteOrder};
+#[cfg(test)]
+use content::{BasicProperties, ContentHeader};
+#[cfg(test)]
+use frame::{self, Frame};
+#[cfg(test)]
+use method::Method;
+#[cfg(test)]
+use protocol;
+
+pub trait Socket: Read + Write {
+    fn shutdown(&self) -> io::Result<()>;
+
+    fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Other, "read timeout not supported"))
+    }
+}
+
+impl Socket for TcpStream {
+    fn shutdown(&self) -> io::Result<()> {
+        TcpStream::shutdown(self, Shutdown::Both)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl Socket for UnixStream {
+    fn shutdown(&self) -> io::Result<()> {
+        UnixStream::shutdown(self, Shutdown::Both)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[derive(Default)]
+struct Pipe {
+    buf: VecDeque<u8>,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Half {
+    pipe: Mutex<Pipe>,
+    ready: Condvar,
+}
+
+impl Half {
+    fn close(&self) {
+        self.pipe.lock().unwrap().closed = true;
+        self.ready.notify_all();
+    }
+}
+
+pub struct MemorySocket {
+    incoming: Arc<Half>,
+    outgoing: Arc<Half>,
+    timeout: Cell<Option<Duration>>,
+}
+
+impl MemorySocket {
+    pub fn pair() -> (MemorySocket, MemorySocket) {
+        let a = Arc::new(Half::default());
+        let b = Arc::new(Half::default());
+        let left = MemorySocket {
+            incoming: a.clone(),
+            outgoing: b.clone(),
+            timeout: Cell::new(None),
+        };
+        let right = MemorySocket {
+            incoming: b,
+            outgoing: a,
+            timeout: Cell::new(None),
+        };
+        (left, right)
+    }
+}
+
+impl Read for MemorySocket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let deadline = self.timeout.get().map(|timeout| Instant::now() + timeout);
+        let mut pipe = self.incoming.pipe.lock().unwrap();
+        while pipe.buf.is_empty() && !pipe.closed && !buf.is_empty() {
+            pipe = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(io::Error::new(io::ErrorKind::TimedOut, "read timed out"));
+                    }
+                    self.incoming.ready.wait_timeout(pipe, deadline - now).unwrap().0
+                },
+                None => self.incoming.ready.wait(pipe).unwrap(),
+            };
+        }
+
+        let n = cmp::min(buf.len(), pipe.buf.len());
+        for (dst, src) in buf.iter_mut().zip(pipe.buf.drain(..n)) {
+            *dst = src;
+        }
+        Ok(n)
+    }
+}
+
+impl Write for MemorySocket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut pipe = self.outgoing.pipe.lock().unwrap();
+        if pipe.closed {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "socket closed"));
+        }
+        pipe.buf.extend(buf);
+        self.outgoing.ready.notify_all();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Socket for MemorySocket {
+    fn shutdown(&self) -> io::Result<()> {
+        self.incoming.close();
+        self.outgoing.close();
+        Ok(())
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.timeout.set(timeout);
+        Ok(())
+    }
+}
+
+impl Drop for MemorySocket {
+    fn drop(&mut self) {
+        let _ = Socket::shutdown(self);
+    }
+}
+
+// Lets a test play the broker on the other end of a pair or of a loopback connection.
+#[cfg(test)]
+pub trait Peer: Read + Write {
+    fn write_frame(&mut self, ty: u8, channel: u16, payload: &[u8]) {
+        let mut buf = Vec::new();
+        frame::encode_frame_into(&mut buf, ty, channel, payload);
+        self.write_all(&buf).unwrap();
+    }
+
+    fn write_method<M: Method>(&mut self, channel: u16, method: &M) {
+        let mut buf = Vec::new();
+        frame::encode_method_into(&mut buf, channel, method).unwrap();
+        self.write_all(&buf).unwrap();
+    }
+
+    fn write_content(&mut self, channel: u16, properties: &BasicProperties, body: &[u8]) {
+        let header = ContentHeader::new(body.len() as u64, properties.clone());
+        self.write_frame(protocol::FRAME_HEADER, channel, &header.se().unwrap());
+        if !body.is_empty() {
+            self.write_frame(protocol::FRAME_BODY, channel, body);
+        }
+    }
+
+    fn read_frame(&mut self) -> Frame {
+        let mut header = [0u8; frame::FRAME_HEADER_SIZE];
+        self.read_exact(&mut header).unwrap();
+        let size = BigEndian::read_u32(&header[3..]) as usize;
+        let mut data = header.to_vec();
+        data.resize(frame::FRAME_HEADER_SIZE + size + 1, 0);
+        self.read_exact(&mut data[frame::FRAME_HEADER_SIZE..]).unwrap();
+        Frame::decode(&data).unwrap().unwrap().0
+    }
+}
+
+#[cfg(test)]
+impl<T: Read + Write> Peer for T {}
+
+#[cfg(test)]
+impl MemorySocket {
+    // The frames written so far, without waiting for more.
+    pub fn read_frames(&mut self) -> Vec<Frame> {
+        let data: Vec<u8> = self.incoming.pipe.lock().unwrap().buf.drain(..).collect();
+        let mut data = &data[..];
+        let mut frames = Vec::new();
+        while let Some((frame, n)) = Frame::decode(data).unwrap() {
+            frames.push(frame);
+            data = &data[n..];
+        }
+        assert!(data.is_empty());
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_memory_socket() {
+        let (mut a, mut b) = MemorySocket::pair();
+        a.write_all(b"ping").unwrap();
+
+        let mut buf = [0u8; 4];
+        b.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        b.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        assert_eq!(b.read(&mut buf).unwrap_err().kind(), io::ErrorKind::TimedOut);
+        b.set_read_timeout(None).unwrap();
+
+        let writer = thread::spawn(move || {
+            a.write_all(b"pong").unwrap();
+        });
+        b.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"pong");
+
+        writer.join().unwrap();
+        assert_eq!(b.read(&mut buf).unwrap(), 0);
+        assert_eq!(b.write(b"x").unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+    }
+}
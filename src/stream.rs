@@ -0,0 +1,111 @@
+use std::cmp;
+
+use channel::Channel;
+use consumer::Consumer;
+use result::*;
+use types::*;
+use types::FieldValue::*;
+
+pub const STREAM_OFFSET: &'static str = "x-stream-offset";
+
+const DEFAULT_PREFETCH_COUNT: u16 = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamOffset {
+    First,
+    Last,
+    Next,
+    Offset(u64),
+    Timestamp(u64),
+    Interval(String),
+}
+
+impl StreamOffset {
+    pub fn to_field_value(&self) -> FieldValue {
+        match *self {
+            StreamOffset::First => LongString(b"first".to_vec()),
+            StreamOffset::Last => LongString(b"last".to_vec()),
+            StreamOffset::Next => LongString(b"next".to_vec()),
+            StreamOffset::Offset(offset) => LongLongUint(offset),
+            StreamOffset::Timestamp(secs) => Timestamp(secs),
+            StreamOffset::Interval(ref interval) => LongString(interval.as_bytes().to_vec()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamConsumer {
+    pub queue: Shortstr,
+    pub consumer_tag: Shortstr,
+    pub offset: StreamOffset,
+    pub prefetch_count: u16,
+}
+
+impl StreamConsumer {
+    pub fn new(queue: &str, offset: StreamOffset) -> StreamConsumer {
+        StreamConsumer {
+            queue: queue.to_string(),
+            consumer_tag: String::new(),
+            offset: offset,
+            prefetch_count: DEFAULT_PREFETCH_COUNT,
+        }
+    }
+
+    pub fn resume(queue: &str, stored_offset: u64) -> StreamConsumer {
+        StreamConsumer::new(queue, StreamOffset::Offset(stored_offset.saturating_add(1)))
+    }
+
+    pub fn consumer(&self) -> Consumer {
+        let mut consumer = Consumer::new(&self.queue);
+        consumer.consumer_tag = self.consumer_tag.clone();
+        consumer.arguments.insert(STREAM_OFFSET.to_string(), self.offset.to_field_value());
+        consumer
+    }
+
+    pub fn consume(&self, channel: &mut Channel) -> AmqpResult<Shortstr> {
+        try!(channel.basic_qos(0, cmp::max(self.prefetch_count, 1), false));
+        channel.basic_consume(&self.consumer())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use consumer::Delivery;
+    use content::BasicProperties;
+
+    #[test]
+    fn test_offset() {
+        assert_eq!(StreamOffset::First.to_field_value(), LongString(b"first".to_vec()));
+        assert_eq!(StreamOffset::Offset(42).to_field_value(), LongLongUint(42));
+        assert_eq!(StreamOffset::Timestamp(1234).to_field_value(), Timestamp(1234));
+        assert_eq!(StreamOffset::Interval("7D".to_string()).to_field_value(),
+                   LongString(b"7D".to_vec()));
+    }
+
+    #[test]
+    fn test_resume() {
+        let consumer = StreamConsumer::resume("events", 41).consumer();
+        assert_eq!(consumer.queue, "events");
+        assert!(!consumer.no_ack);
+        assert_eq!(consumer.arguments[STREAM_OFFSET], LongLongUint(42));
+
+        let consumer = StreamConsumer::resume("events", u64::max_value()).consumer();
+        assert_eq!(consumer.arguments[STREAM_OFFSET], LongLongUint(u64::max_value()));
+    }
+
+    #[test]
+    fn test_stream_offset() {
+        let mut properties = BasicProperties::default();
+        assert_eq!(Delivery::for_test(properties.clone(), b"").stream_offset(), None);
+
+        let mut headers = Table::new();
+        headers.insert(STREAM_OFFSET.to_string(), LongLongInt(42));
+        properties.headers = Some(headers.clone());
+        assert_eq!(Delivery::for_test(properties.clone(), b"").stream_offset(), Some(42));
+
+        headers.insert(STREAM_OFFSET.to_string(), LongString(b"42".to_vec()));
+        properties.headers = Some(headers);
+        assert_eq!(Delivery::for_test(properties, b"").stream_offset(), None);
+    }
+}
@@ -1,66 +1,349 @@
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-
-use std::io::{Read, Write};
-use std::net::Shutdown;
+use std::collections::{HashMap, VecDeque};
+use std::cmp;
+use std::io::{self, Read, Write};
 use std::net::TcpStream;
+use std::time::{Duration, Instant};
 
+use channel::CloseReason;
 use conf::Conf;
-use frame::Frame;
+use engine::{self, Engine, Event, State};
+use frame::{self, Frame};
+use method::Method;
 use protocol;
 use result::*;
+use socket::Socket;
+
+// Buffered frames are written out early once this many bytes are waiting, so a large body
+// does not have to fit in the output buffer as a whole.
+const OUTPUT_SPILL: usize = 128 * 1024;
+
+// Where the buffered output stood, see Transport::rollback.
+#[derive(Debug, Clone, Copy)]
+pub struct Mark {
+    len: usize,
+    writes: u64,
+}
 
 pub struct Transport {
-    stream: TcpStream,
+    stream: Box<Socket>,
+    engine: Engine,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    writes: u64,
+    pending: HashMap<u16, VecDeque<Frame>>,
+    timeout: Option<Duration>,
+    opened: bool,
+    closed: bool,
+    close_reason: Option<CloseReason>,
 }
 
 impl Transport {
     pub fn new(conf: &Conf) -> Transport {
+        let stream = TcpStream::connect((&*conf.host, conf.port)).unwrap();
+        Transport::with_socket(conf, Box::new(stream))
+    }
+
+    pub fn with_socket(conf: &Conf, stream: Box<Socket>) -> Transport {
         Transport {
-            stream: TcpStream::connect((&*conf.host, conf.port)).unwrap(),
+            stream: stream,
+            engine: Engine::new(conf.clone()),
+            input: vec![0u8; engine::INPUT_CHUNK],
+            output: Vec::new(),
+            writes: 0,
+            pending: HashMap::new(),
+            timeout: None,
+            opened: false,
+            closed: false,
+            close_reason: None,
+        }
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    // Why the broker closed the connection, if it did.
+    pub fn close_reason(&self) -> Option<&CloseReason> {
+        self.close_reason.as_ref()
+    }
+
+    pub fn open(&mut self) -> AmqpResult<()> {
+        self.engine.start(Instant::now());
+        try!(self.flush());
+        while !self.opened {
+            try!(self.check_closed());
+            try!(self.recv_next());
         }
+        Ok(())
     }
 
-    pub fn send_header(&mut self) -> AmqpResult<()> {
-        try!(self.stream.write_all(&protocol::PROT_HEADER));
+    pub fn close(&mut self) -> AmqpResult<()> {
+        try!(self.engine.close());
+        try!(self.flush());
+        while self.engine.state() == State::Closing {
+            try!(self.recv_next());
+        }
+        try!(self.stream.shutdown());
         Ok(())
     }
 
     pub fn send(&mut self, frame: Frame) -> AmqpResult<()> {
-        try!(self.stream.write_u8(frame.ty));
-        try!(self.stream.write_u16::<BigEndian>(frame.channel));
-        try!(self.stream.write_u32::<BigEndian>(frame.payload.len() as u32));
-        try!(self.stream.write_all(&frame.payload));
-        try!(self.stream.write_u8(protocol::FRAME_END));
+        self.send_frame(frame.ty, frame.channel, &frame.payload)
+    }
+
+    pub fn send_frame(&mut self, ty: u8, channel: u16, payload: &[u8]) -> AmqpResult<()> {
+        frame::encode_frame_into(&mut self.output, ty, channel, payload);
+        self.flush()
+    }
+
+    // write_frame and write_method only buffer; the frames of a message go out together on
+    // the next flush, which every send and receive does first.
+    pub fn write_frame(&mut self, ty: u8, channel: u16, payload: &[u8]) -> AmqpResult<()> {
+        frame::encode_frame_into(&mut self.output, ty, channel, payload);
+        self.spill()
+    }
+
+    pub fn write_method<M: Method>(&mut self, channel: u16, method: &M) -> AmqpResult<()> {
+        try!(frame::encode_method_into(&mut self.output, channel, method));
+        self.spill()
+    }
+
+    pub fn mark(&self) -> Mark {
+        Mark {
+            len: self.output.len(),
+            writes: self.writes,
+        }
+    }
+
+    // Drops the frames buffered since the mark. Returns false if some of them had already
+    // been written out; the rest are dropped all the same.
+    pub fn rollback(&mut self, mark: Mark) -> bool {
+        if self.writes == mark.writes {
+            self.output.truncate(mark.len);
+            true
+        } else {
+            self.output.clear();
+            false
+        }
+    }
+
+    pub fn flush(&mut self) -> AmqpResult<()> {
+        let now = Instant::now();
+        if let Some(data) = self.engine.poll_transmit(now) {
+            self.output.extend_from_slice(&data);
+        }
+        if self.output.is_empty() {
+            return Ok(());
+        }
+
+        self.writes += 1;
+        let result = self.stream.write_all(&self.output);
+        if self.output.capacity() > 2 * OUTPUT_SPILL {
+            self.output = Vec::new();
+        } else {
+            self.output.clear();
+        }
+        try!(result);
+        self.engine.on_transmit(now);
         Ok(())
     }
 
-    pub fn recv(&mut self) -> AmqpResult<Frame> {
-        let mut header = &mut [0u8; 7] as &mut [u8];
-        try!(self.stream.read_exact(&mut header));
-        let header = &mut (header as &[u8]) as &mut &[u8];
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
 
-        let ty = try!(header.read_u8());
-        let channel = try!(header.read_u16::<BigEndian>());
-        let size = try!(header.read_u32::<BigEndian>()) as usize;
+    pub fn recv_on(&mut self, channel: u16) -> AmqpResult<Frame> {
+        loop {
+            if let Some(frame) = self.pending.get_mut(&channel).and_then(|f| f.pop_front()) {
+                return Ok(frame);
+            }
+            try!(self.check_closed());
+            try!(self.recv_next());
+        }
+    }
 
-        let mut payload = vec![0u8; size];
-        try!(self.stream.read_exact(&mut payload));
+    // Copies content body bytes of the channel into buf from its next body frame, reading the
+    // socket only once none is queued, so a large body is never held in memory as a whole.
+    pub fn read_body(&mut self, channel: u16, buf: &mut [u8]) -> AmqpResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
 
-        let frame_end = try!(self.stream.read_u8());
-        if frame_end != protocol::FRAME_END {
-            return Err(AmqpError::FrameEndErr(frame_end));
+        loop {
+            if let Some(frames) = self.pending.get_mut(&channel) {
+                if let Some(mut frame) = frames.pop_front() {
+                    if frame.ty != protocol::FRAME_BODY {
+                        return Err(AmqpError::FrameTypeErr(frame.ty, protocol::FRAME_BODY));
+                    }
+                    let n = cmp::min(buf.len(), frame.payload.len());
+                    buf[..n].copy_from_slice(&frame.payload[..n]);
+                    if n < frame.payload.len() {
+                        let _ = frame.payload.split_to(n);
+                        frames.push_front(frame);
+                    }
+                    return Ok(n);
+                }
+            }
+            try!(self.check_closed());
+            try!(self.recv_next());
         }
+    }
 
-        let frame = Frame {
-            ty: ty,
-            channel: channel,
-            payload: payload,
-        };
-        Ok(frame)
+    // Hands the next chunk read from the socket to the engine and queues the channel frames
+    // it cuts out of it.
+    fn recv_next(&mut self) -> AmqpResult<()> {
+        let n = try!(self.wait_readable());
+        let result = self.engine.handle_input(&self.input[..n], Instant::now());
+        self.poll_events();
+        try!(self.flush());
+        result
     }
 
-    pub fn close(&self) -> AmqpResult<()> {
-        try!(self.stream.shutdown(Shutdown::Both));
+    fn poll_events(&mut self) {
+        while let Some(event) = self.engine.poll_event() {
+            match event {
+                Event::Opened => self.opened = true,
+                Event::Closed(reason) => {
+                    self.closed = true;
+                    self.close_reason = reason;
+                },
+                Event::Frame(frame) => {
+                    self.pending.entry(frame.channel).or_insert_with(VecDeque::new)
+                        .push_back(frame);
+                },
+            }
+        }
+    }
+
+    fn spill(&mut self) -> AmqpResult<()> {
+        if self.output.len() >= OUTPUT_SPILL {
+            try!(self.flush());
+        }
         Ok(())
     }
+
+    fn check_closed(&self) -> AmqpResult<()> {
+        if self.closed {
+            Err(AmqpError::ConnectionClosed)
+        } else {
+            Ok(())
+        }
+    }
+
+    // The timeout covers the wait for the next bytes; a frame cut short by it stays buffered
+    // in the engine. While waiting, the engine's heartbeat timer is served as well.
+    fn wait_readable(&mut self) -> AmqpResult<usize> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let now = Instant::now();
+            let result = self.engine.handle_timeout(now);
+            self.poll_events();
+            try!(result);
+            try!(self.flush());
+
+            let wake = match (deadline, self.engine.poll_timeout()) {
+                (Some(a), Some(b)) => cmp::min(a, b),
+                (Some(a), None) | (None, Some(a)) => a,
+                (None, None) => return read_some(&mut self.stream, &mut self.input),
+            };
+            if deadline.map_or(false, |deadline| now >= deadline) {
+                return Err(AmqpError::Timeout);
+            }
+
+            let wait = if wake > now { wake - now } else { Duration::from_millis(1) };
+            try!(self.stream.set_read_timeout(Some(wait)));
+            let result = read_some(&mut self.stream, &mut self.input);
+            try!(self.stream.set_read_timeout(None));
+            match result {
+                Err(AmqpError::IoErr(ref err)) if err.kind() == io::ErrorKind::WouldBlock ||
+                                                  err.kind() == io::ErrorKind::TimedOut => (),
+                result => return result,
+            }
+        }
+    }
+}
+
+fn read_some(stream: &mut Box<Socket>, buf: &mut [u8]) -> AmqpResult<usize> {
+    match try!(stream.read(buf)) {
+        0 => Err(AmqpError::IoErr(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                                 "connection closed by peer"))),
+        n => Ok(n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use content::ContentHeader;
+    use protocol::{basic, connection};
+    use socket::{MemorySocket, Peer};
+
+    fn pair() -> (Transport, MemorySocket) {
+        let (client, server) = MemorySocket::pair();
+        (Transport::with_socket(&Conf::default(), Box::new(client)), server)
+    }
+
+    #[test]
+    fn test_read_body() {
+        let (mut tx, mut server) = pair();
+        server.write_method(0, &connection::Start::default());
+        server.write_method(0, &connection::Tune::default());
+        server.write_method(0, &connection::OpenOk::default());
+        tx.open().unwrap();
+        server.write_frame(protocol::FRAME_BODY, 1, b"hello world");
+        server.write_frame(protocol::FRAME_HEARTBEAT, 0, b"");
+        server.write_frame(protocol::FRAME_METHOD, 2, &[0, 60, 0, 80]);
+        server.write_frame(protocol::FRAME_BODY, 1, b"!");
+
+        let mut buf = [0u8; 64];
+        let n = tx.read_body(1, &mut buf[..4]).unwrap();
+        assert_eq!(&buf[..n], b"hell");
+
+        let frame = tx.recv_on(2).unwrap();
+        assert_eq!(frame.payload, vec![0, 60, 0, 80]);
+
+        let n = tx.read_body(1, &mut buf).unwrap();
+        assert_eq!(&buf[..n], b"o world");
+        let n = tx.read_body(1, &mut buf).unwrap();
+        assert_eq!(&buf[..n], b"!");
+    }
+
+    #[test]
+    fn test_write_buffered() {
+        let (mut tx, mut server) = pair();
+        let header = ContentHeader::new(11, Default::default());
+        tx.write_method(1, &basic::Publish::default()).unwrap();
+        tx.write_frame(protocol::FRAME_HEADER, 1, &header.se().unwrap()).unwrap();
+        tx.write_frame(protocol::FRAME_BODY, 1, b"hello").unwrap();
+        tx.write_frame(protocol::FRAME_BODY, 1, b" world").unwrap();
+        assert_eq!(tx.writes, 0);
+        assert!(server.read_frames().is_empty());
+
+        tx.flush().unwrap();
+        assert_eq!(tx.writes, 1);
+        let frames = server.read_frames();
+        assert_eq!(frames.len(), 4);
+        let _: basic::Publish = frames[0].to_method().unwrap();
+        assert_eq!(frames[1].to_content_header().unwrap(), header);
+        assert_eq!(frames[3].payload, &b" world"[..]);
+
+        tx.flush().unwrap();
+        assert_eq!(tx.writes, 1);
+    }
+
+    #[test]
+    fn test_write_spill() {
+        let (mut tx, mut server) = pair();
+        let chunk = vec![0u8; OUTPUT_SPILL / 4];
+        for _ in 0..4 {
+            tx.write_frame(protocol::FRAME_BODY, 1, &chunk).unwrap();
+        }
+        assert_eq!(tx.writes, 1);
+        assert_eq!(server.read_frames().len(), 4);
+
+        tx.write_frame(protocol::FRAME_BODY, 1, &chunk).unwrap();
+        tx.flush().unwrap();
+        assert_eq!(tx.writes, 2);
+        assert_eq!(server.read_frames().len(), 1);
+    }
 }
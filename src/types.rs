@@ -1,9 +1,10 @@
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::str;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{i64, u64};
 
-pub enum StrType {
-    Short,
-    Long,
-}
+use result::*;
 
 pub type Bit = bool;
 pub type Octet = u8;
@@ -16,7 +17,7 @@ pub type Longstr = Vec<u8>;
 pub type FieldName = Shortstr;
 pub type Table = HashMap<FieldName, FieldValue>;
 
-#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum FieldValue {
     Bool(bool),
     ShortShortInt(i8),
@@ -43,3 +44,287 @@ pub fn to_string(s: Longstr) -> String {
         String::from_utf8_unchecked(s)
     }
 }
+
+#[macro_export]
+macro_rules! table {
+    () => ($crate::types::Table::new());
+    ($($key:expr => $value:expr),+ $(,)*) => ({
+        let mut table = $crate::types::Table::new();
+        $(table.insert($key.to_string(), $crate::types::FieldValue::from($value));)+
+        table
+    });
+}
+
+impl FieldValue {
+    pub fn array<T, I>(values: I) -> FieldValue
+    where T: Into<FieldValue>, I: IntoIterator<Item = T> {
+        FieldValue::FieldArray(values.into_iter().map(Into::into).collect())
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            FieldValue::ShortShortInt(v) => Some(v as i64),
+            FieldValue::ShortShortUint(v) => Some(v as i64),
+            FieldValue::ShortInt(v) => Some(v as i64),
+            FieldValue::ShortUint(v) => Some(v as i64),
+            FieldValue::LongInt(v) => Some(v as i64),
+            FieldValue::LongUint(v) => Some(v as i64),
+            FieldValue::LongLongInt(v) => Some(v),
+            FieldValue::LongLongUint(v) if v <= i64::MAX as u64 => Some(v as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            FieldValue::LongLongUint(v) => Some(v),
+            _ => self.as_i64().and_then(|v| if v >= 0 { Some(v as u64) } else { None }),
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            FieldValue::Float(v) => Some(v as f64),
+            FieldValue::Double(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            FieldValue::Bool(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            FieldValue::ShortString(ref s) => Some(s),
+            FieldValue::LongString(ref s) => str::from_utf8(s).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match *self {
+            FieldValue::ShortString(ref s) => Some(s.as_bytes()),
+            FieldValue::LongString(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[FieldValue]> {
+        match *self {
+            FieldValue::FieldArray(ref v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&Table> {
+        match *self {
+            FieldValue::FieldTable(ref v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_timestamp(&self) -> Option<SystemTime> {
+        match *self {
+            FieldValue::Timestamp(secs) => Some(UNIX_EPOCH + Duration::from_secs(secs)),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! from_impl {
+    ($ty:ty, $variant:ident) => (
+        impl From<$ty> for FieldValue {
+            fn from(v: $ty) -> FieldValue {
+                FieldValue::$variant(v)
+            }
+        }
+    )
+}
+
+from_impl!(bool, Bool);
+from_impl!(i8, ShortShortInt);
+from_impl!(u8, ShortShortUint);
+from_impl!(i16, ShortInt);
+from_impl!(u16, ShortUint);
+from_impl!(i32, LongInt);
+from_impl!(u32, LongUint);
+from_impl!(i64, LongLongInt);
+from_impl!(u64, LongLongUint);
+from_impl!(f32, Float);
+from_impl!(f64, Double);
+from_impl!(Vec<u8>, LongString);
+from_impl!(Vec<FieldValue>, FieldArray);
+
+impl From<String> for FieldValue {
+    fn from(v: String) -> FieldValue {
+        FieldValue::LongString(v.into_bytes())
+    }
+}
+
+impl<'a> From<&'a str> for FieldValue {
+    fn from(v: &'a str) -> FieldValue {
+        FieldValue::LongString(v.as_bytes().to_vec())
+    }
+}
+
+impl<T: Into<FieldValue>> From<HashMap<String, T>> for FieldValue {
+    fn from(v: HashMap<String, T>) -> FieldValue {
+        FieldValue::FieldTable(v.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+impl From<SystemTime> for FieldValue {
+    fn from(v: SystemTime) -> FieldValue {
+        let secs = v.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        FieldValue::Timestamp(secs)
+    }
+}
+
+macro_rules! try_from_impl {
+    ($ty:ty, $name:expr, $v:ident => $conv:expr) => (
+        impl<'a> TryFrom<&'a FieldValue> for $ty {
+            type Error = AmqpError;
+
+            fn try_from($v: &'a FieldValue) -> AmqpResult<$ty> {
+                match $conv {
+                    Some(v) => Ok(v),
+                    None => Err(AmqpError::FieldTypeErr($name)),
+                }
+            }
+        }
+
+        impl TryFrom<FieldValue> for $ty {
+            type Error = AmqpError;
+
+            fn try_from(v: FieldValue) -> AmqpResult<$ty> {
+                <$ty as TryFrom<&FieldValue>>::try_from(&v)
+            }
+        }
+    )
+}
+
+try_from_impl!(bool, "bool", v => v.as_bool());
+try_from_impl!(i8, "i8", v => v.as_i64().and_then(|v| i8::try_from(v).ok()));
+try_from_impl!(u8, "u8", v => v.as_u64().and_then(|v| u8::try_from(v).ok()));
+try_from_impl!(i16, "i16", v => v.as_i64().and_then(|v| i16::try_from(v).ok()));
+try_from_impl!(u16, "u16", v => v.as_u64().and_then(|v| u16::try_from(v).ok()));
+try_from_impl!(i32, "i32", v => v.as_i64().and_then(|v| i32::try_from(v).ok()));
+try_from_impl!(u32, "u32", v => v.as_u64().and_then(|v| u32::try_from(v).ok()));
+try_from_impl!(i64, "i64", v => v.as_i64());
+try_from_impl!(u64, "u64", v => v.as_u64());
+try_from_impl!(f64, "f64", v => v.as_f64());
+try_from_impl!(String, "string", v => v.as_str().map(|s| s.to_string()));
+try_from_impl!(Vec<u8>, "bytes", v => v.as_bytes().map(|s| s.to_vec()));
+try_from_impl!(Vec<FieldValue>, "array", v => v.as_array().map(|a| a.to_vec()));
+try_from_impl!(Table, "table", v => v.as_table().cloned());
+try_from_impl!(SystemTime, "timestamp", v => v.as_timestamp());
+
+pub trait TableExt {
+    fn get_bool(&self, key: &str) -> Option<bool>;
+    fn get_i64(&self, key: &str) -> Option<i64>;
+    fn get_u64(&self, key: &str) -> Option<u64>;
+    fn get_f64(&self, key: &str) -> Option<f64>;
+    fn get_str(&self, key: &str) -> Option<&str>;
+    fn get_bytes(&self, key: &str) -> Option<&[u8]>;
+    fn get_array(&self, key: &str) -> Option<&[FieldValue]>;
+    fn get_table(&self, key: &str) -> Option<&Table>;
+    fn get_timestamp(&self, key: &str) -> Option<SystemTime>;
+}
+
+impl TableExt for Table {
+    fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(FieldValue::as_bool)
+    }
+
+    fn get_i64(&self, key: &str) -> Option<i64> {
+        self.get(key).and_then(FieldValue::as_i64)
+    }
+
+    fn get_u64(&self, key: &str) -> Option<u64> {
+        self.get(key).and_then(FieldValue::as_u64)
+    }
+
+    fn get_f64(&self, key: &str) -> Option<f64> {
+        self.get(key).and_then(FieldValue::as_f64)
+    }
+
+    fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(FieldValue::as_str)
+    }
+
+    fn get_bytes(&self, key: &str) -> Option<&[u8]> {
+        self.get(key).and_then(FieldValue::as_bytes)
+    }
+
+    fn get_array(&self, key: &str) -> Option<&[FieldValue]> {
+        self.get(key).and_then(FieldValue::as_array)
+    }
+
+    fn get_table(&self, key: &str) -> Option<&Table> {
+        self.get(key).and_then(FieldValue::as_table)
+    }
+
+    fn get_timestamp(&self, key: &str) -> Option<SystemTime> {
+        self.get(key).and_then(FieldValue::as_timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+    use super::FieldValue::*;
+
+    #[test]
+    fn test_from() {
+        assert_eq!(FieldValue::from(true), Bool(true));
+        assert_eq!(FieldValue::from(-1i8), ShortShortInt(-1));
+        assert_eq!(FieldValue::from(42u64), LongLongUint(42));
+        assert_eq!(FieldValue::from(1.5f64), Double(1.5));
+        assert_eq!(FieldValue::from("s"), LongString(b"s".to_vec()));
+        assert_eq!(FieldValue::from(vec![1u8, 2]), LongString(vec![1, 2]));
+        assert_eq!(FieldValue::array(vec![1i32, 2]), FieldArray(vec![LongInt(1), LongInt(2)]));
+        assert_eq!(FieldValue::from(UNIX_EPOCH + Duration::from_secs(60)), Timestamp(60));
+    }
+
+    #[test]
+    fn test_try_from() {
+        assert_eq!(i64::try_from(&ShortUint(7)).unwrap(), 7);
+        assert_eq!(u64::try_from(LongInt(7)).unwrap(), 7);
+        assert!(u64::try_from(LongInt(-7)).is_err());
+        assert!(i8::try_from(LongInt(300)).is_err());
+        assert!(i64::try_from(LongLongUint(u64::MAX)).is_err());
+        assert_eq!(String::try_from(ShortString("s".to_string())).unwrap(), "s");
+        assert!(bool::try_from(Void).is_err());
+    }
+
+    #[test]
+    fn test_table() {
+        let nested = table!{ "n" => 1u8 };
+        let table = table!{
+            "a" => true,
+            "b" => -3i16,
+            "c" => 4u32,
+            "d" => "text",
+            "e" => nested.clone(),
+            "f" => vec![Void],
+        };
+
+        assert_eq!(table.get_bool("a"), Some(true));
+        assert_eq!(table.get_i64("b"), Some(-3));
+        assert_eq!(table.get_u64("b"), None);
+        assert_eq!(table.get_i64("c"), Some(4));
+        assert_eq!(table.get_u64("c"), Some(4));
+        assert_eq!(table.get_str("d"), Some("text"));
+        assert_eq!(table.get_table("e"), Some(&nested));
+        assert_eq!(table.get_array("f"), Some(&[Void][..]));
+        assert_eq!(table.get_str("missing"), None);
+        assert!(table!{}.is_empty());
+    }
+}